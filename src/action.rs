@@ -0,0 +1,377 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::cnab::{Bundle, Credential};
+
+/// BundleAction names an action that a bundle can perform.
+///
+/// `install`, `upgrade` and `uninstall` are built into every bundle. Any other name is a
+/// custom action, which must be declared in `Bundle::actions`.
+///
+/// Actions compare, order and hash by name, so `BundleAction::Custom("install".into())`
+/// is the same action as `BundleAction::Install`. Converting from a string always yields
+/// the built-in variant for a built-in name.
+///
+/// ```
+/// use libcnab::BundleAction;
+///
+/// assert_eq!(BundleAction::from("install"), BundleAction::Install);
+/// assert_eq!(BundleAction::from("status"), BundleAction::Custom("status".to_string()));
+/// assert_eq!(BundleAction::Uninstall.to_string(), "uninstall");
+/// ```
+#[derive(Debug, Clone)]
+pub enum BundleAction {
+    Install,
+    Upgrade,
+    Uninstall,
+    Custom(String),
+}
+
+impl BundleAction {
+    /// The actions built into every bundle.
+    pub const BUILT_IN: [BundleAction; 3] = [
+        BundleAction::Install,
+        BundleAction::Upgrade,
+        BundleAction::Uninstall,
+    ];
+
+    /// The name of the action.
+    pub fn as_str(&self) -> &str {
+        match self {
+            BundleAction::Install => "install",
+            BundleAction::Upgrade => "upgrade",
+            BundleAction::Uninstall => "uninstall",
+            BundleAction::Custom(name) => name,
+        }
+    }
+
+    /// Returns true if this is one of the actions built into every bundle.
+    pub fn is_built_in(&self) -> bool {
+        Self::BUILT_IN.iter().any(|a| a.as_str() == self.as_str())
+    }
+}
+
+impl From<&str> for BundleAction {
+    fn from(name: &str) -> Self {
+        match name {
+            "install" => BundleAction::Install,
+            "upgrade" => BundleAction::Upgrade,
+            "uninstall" => BundleAction::Uninstall,
+            _ => BundleAction::Custom(name.to_string()),
+        }
+    }
+}
+
+impl From<String> for BundleAction {
+    fn from(name: String) -> Self {
+        match BundleAction::from(name.as_str()) {
+            BundleAction::Custom(_) => BundleAction::Custom(name),
+            built_in => built_in,
+        }
+    }
+}
+
+impl FromStr for BundleAction {
+    type Err = Infallible;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(BundleAction::from(name))
+    }
+}
+
+impl std::fmt::Display for BundleAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq for BundleAction {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for BundleAction {}
+
+impl PartialEq<str> for BundleAction {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for BundleAction {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialOrd for BundleAction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BundleAction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for BundleAction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+/// Allows maps keyed by action, such as `Bundle::actions`, to be indexed by name.
+impl Borrow<str> for BundleAction {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Serialize for BundleAction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for BundleAction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(BundleAction::from)
+    }
+}
+
+/// Actions.
+impl Bundle {
+    /// Every action this bundle can perform: the built-in actions, followed by the custom
+    /// actions it declares.
+    pub fn available_actions(&self) -> Vec<BundleAction> {
+        let mut actions = BundleAction::BUILT_IN.to_vec();
+        for name in self.actions.iter().flat_map(|actions| actions.keys()) {
+            if !name.is_built_in() {
+                actions.push(name.clone());
+            }
+        }
+        actions
+    }
+
+    /// Returns whether `action` needs no installation state, or `None` if the bundle does
+    /// not have the action.
+    ///
+    /// The built-in actions are never stateless.
+    pub fn action_is_stateless(&self, action: &BundleAction) -> Option<bool> {
+        if action.is_built_in() {
+            return Some(false);
+        }
+        self.actions.as_ref()?.get(action).map(|a| a.stateless)
+    }
+
+    /// Returns whether `action` modifies the installation, or `None` if the bundle does not
+    /// have the action.
+    ///
+    /// The built-in actions always modify the installation.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, BundleAction};
+    ///
+    /// let bundle: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0",
+    ///     "actions": {"status": {"stateless": true}}
+    /// }"#.parse().unwrap();
+    ///
+    /// assert_eq!(bundle.action_modifies(&BundleAction::Upgrade), Some(true));
+    /// assert_eq!(bundle.action_modifies(&"status".into()), Some(false));
+    /// assert_eq!(bundle.action_modifies(&"explode".into()), None);
+    /// ```
+    pub fn action_modifies(&self, action: &BundleAction) -> Option<bool> {
+        if action.is_built_in() {
+            return Some(true);
+        }
+        self.actions.as_ref()?.get(action).map(|a| a.modifies)
+    }
+
+    /// Returns true if this bundle can perform `action`.
+    pub fn has_action(&self, action: &BundleAction) -> bool {
+        action.is_built_in()
+            || self
+                .actions
+                .iter()
+                .any(|actions| actions.contains_key(action))
+    }
+
+    /// The credentials that apply to `action`, in name order, with whether each must be
+    /// supplied.
+    ///
+    /// A credential applies unless its `applyTo` list leaves the action out. Stateless
+    /// actions run without installation state, so none of their credentials are required.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, BundleAction};
+    ///
+    /// let bundle: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0",
+    ///     "credentials": {
+    ///         "kubeconfig": {"path": "/root/.kube/config", "required": true},
+    ///         "registry": {"env": "REGISTRY_TOKEN", "required": true, "applyTo": ["install"]}
+    ///     }
+    /// }"#.parse().unwrap();
+    ///
+    /// let credentials = bundle.credentials_for_action(&BundleAction::Uninstall);
+    /// assert_eq!(credentials.len(), 1);
+    /// assert_eq!(credentials[0].name, "kubeconfig");
+    /// assert!(credentials[0].required);
+    /// ```
+    pub fn credentials_for_action(&self, action: &BundleAction) -> Vec<ActionCredential<'_>> {
+        let stateless = self.action_is_stateless(action).unwrap_or(false);
+        self.credentials
+            .iter()
+            .flatten()
+            .filter(|(_, credential)| credential.applies_to(action))
+            .map(|(name, credential)| ActionCredential {
+                name,
+                credential,
+                required: !stateless && credential.required.unwrap_or(false),
+            })
+            .collect()
+    }
+}
+
+/// ActionCredential is a credential that applies to a particular action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActionCredential<'a> {
+    /// The name of the credential
+    pub name: &'a str,
+    /// The credential, as declared by the bundle
+    pub credential: &'a Credential,
+    /// Whether the credential must be supplied to run the action
+    pub required: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_bundle_action_names() {
+        assert_eq!(
+            BundleAction::from("upgrade".to_string()),
+            BundleAction::Upgrade
+        );
+        assert_eq!(
+            BundleAction::Custom("install".to_string()),
+            BundleAction::Install
+        );
+        assert!(BundleAction::Custom("uninstall".to_string()).is_built_in());
+        assert!(!BundleAction::from("status").is_built_in());
+        assert_eq!(BundleAction::from("status"), "status");
+
+        let actions: Vec<BundleAction> =
+            serde_json::from_str(r#"["install", "io.cnab.status"]"#).expect("actions");
+        assert_eq!(
+            actions,
+            vec![
+                BundleAction::Install,
+                BundleAction::Custom("io.cnab.status".to_string())
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&actions).expect("serialized"),
+            r#"["install","io.cnab.status"]"#
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert(BundleAction::from("status"), 1);
+        assert_eq!(map.get("status"), Some(&1));
+    }
+
+    #[test]
+    fn test_bundle_actions() {
+        let bun: Bundle = r#"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "actions": {
+                "status": {"stateless": true},
+                "backup": {"modifies": true}
+            }
+        }"#
+        .parse()
+        .expect("parsed bundle");
+
+        let names: Vec<String> = bun
+            .available_actions()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            names,
+            vec!["install", "upgrade", "uninstall", "backup", "status"]
+        );
+
+        let status = BundleAction::from("status");
+        let backup = BundleAction::from("backup");
+        let missing = BundleAction::from("missing");
+        assert_eq!(bun.action_is_stateless(&BundleAction::Install), Some(false));
+        assert_eq!(bun.action_is_stateless(&status), Some(true));
+        assert_eq!(bun.action_is_stateless(&missing), None);
+        assert_eq!(bun.action_modifies(&BundleAction::Uninstall), Some(true));
+        assert_eq!(bun.action_modifies(&backup), Some(true));
+        assert_eq!(bun.action_modifies(&status), Some(false));
+        assert!(bun.has_action(&status));
+        assert!(!bun.has_action(&missing));
+    }
+
+    #[test]
+    fn test_credentials_for_action() {
+        let bun: Bundle = r#"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "actions": {
+                "status": {"stateless": true},
+                "backup": {"modifies": true}
+            },
+            "credentials": {
+                "kubeconfig": {"path": "/root/.kube/config", "required": true},
+                "registry": {"env": "REGISTRY_TOKEN", "required": true, "applyTo": ["install", "upgrade"]},
+                "storage": {"env": "STORAGE_KEY", "applyTo": ["backup", "status"]}
+            }
+        }"#
+        .parse()
+        .expect("parsed bundle");
+
+        let summary = |action: &str| -> Vec<(&str, bool)> {
+            bun.credentials_for_action(&action.into())
+                .iter()
+                .map(|c| (c.name, c.required))
+                .collect()
+        };
+        assert_eq!(
+            summary("install"),
+            vec![("kubeconfig", true), ("registry", true)]
+        );
+        assert_eq!(summary("uninstall"), vec![("kubeconfig", true)]);
+        assert_eq!(
+            summary("backup"),
+            vec![("kubeconfig", true), ("storage", false)]
+        );
+        assert_eq!(
+            summary("status"),
+            vec![("kubeconfig", false), ("storage", false)]
+        );
+    }
+}
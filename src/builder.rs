@@ -0,0 +1,652 @@
+use semver::Version;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::action::BundleAction;
+use crate::cnab::*;
+use crate::digest::Digest;
+use crate::schema::Schema;
+use crate::validation::ValidationError;
+
+/// The CNAB specification version that new bundles declare by default.
+pub const CNAB_SCHEMA_VERSION: &str = "v1.0.0";
+
+/// BundleBuilder constructs a `Bundle` programmatically.
+///
+/// Every optional field starts unset, and `schema_version` defaults to
+/// `CNAB_SCHEMA_VERSION`. `build()` runs `Bundle::validate`, so a builder cannot produce
+/// a bundle that violates the specification.
+///
+/// ```
+/// use libcnab::{BundleBuilder, InvocationImageBuilder, ParameterBuilder, Schema};
+/// use semver::Version;
+///
+/// let bundle = BundleBuilder::new("helloworld", Version::new(0, 1, 0))
+///     .description("An example bundle")
+///     .invocation_image(InvocationImageBuilder::new("technosophos/helloworld:0.1.0"))
+///     .definition("port", serde_json::from_str::<Schema>(r#"{"type": "integer"}"#).unwrap())
+///     .parameter("port", ParameterBuilder::new().env("PORT").definition("port"))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(bundle.name, "helloworld");
+/// assert_eq!(bundle.schema_version, "v1.0.0");
+/// ```
+#[derive(Debug)]
+pub struct BundleBuilder {
+    bundle: Bundle,
+}
+
+impl BundleBuilder {
+    /// Start a bundle with the given name and version.
+    pub fn new<S: Into<String>>(name: S, version: Version) -> Self {
+        BundleBuilder {
+            bundle: Bundle {
+                actions: None,
+                credentials: None,
+                custom: None,
+                definitions: None,
+                description: None,
+                images: None,
+                invocation_images: Vec::new(),
+                keywords: None,
+                license: None,
+                maintainers: None,
+                name: name.into(),
+                outputs: None,
+                parameters: None,
+                required_extensions: None,
+                schema_version: CNAB_SCHEMA_VERSION.to_string(),
+                version,
+                unknown_fields: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Set the version of the CNAB specification the bundle declares.
+    pub fn schema_version<S: Into<String>>(mut self, schema_version: S) -> Self {
+        self.bundle.schema_version = schema_version.into();
+        self
+    }
+
+    /// Set the description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.bundle.description = Some(description.into());
+        self
+    }
+
+    /// Set the SPDX license identifier.
+    pub fn license<S: Into<String>>(mut self, license: S) -> Self {
+        self.bundle.license = Some(license.into());
+        self
+    }
+
+    /// Add a keyword.
+    pub fn keyword<S: Into<String>>(mut self, keyword: S) -> Self {
+        self.bundle
+            .keywords
+            .get_or_insert_with(Vec::new)
+            .push(keyword.into());
+        self
+    }
+
+    /// Add a maintainer.
+    pub fn maintainer(mut self, maintainer: Maintainer) -> Self {
+        self.bundle
+            .maintainers
+            .get_or_insert_with(Vec::new)
+            .push(maintainer);
+        self
+    }
+
+    /// Add an invocation image.
+    pub fn invocation_image<I: Into<InvocationImage>>(mut self, image: I) -> Self {
+        self.bundle.invocation_images.push(image.into());
+        self
+    }
+
+    /// Add a named image.
+    pub fn image<S: Into<String>, I: Into<Image>>(mut self, name: S, image: I) -> Self {
+        insert(&mut self.bundle.images, name, image.into());
+        self
+    }
+
+    /// Add a custom action.
+    pub fn action<A: Into<BundleAction>>(mut self, name: A, action: Action) -> Self {
+        self.bundle
+            .actions
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.into(), action);
+        self
+    }
+
+    /// Add a named definition.
+    pub fn definition<S: Into<String>>(mut self, name: S, schema: Schema) -> Self {
+        insert(&mut self.bundle.definitions, name, schema.into());
+        self
+    }
+
+    /// Add a parameter.
+    pub fn parameter<S: Into<String>, P: Into<Parameter>>(mut self, name: S, parameter: P) -> Self {
+        insert(&mut self.bundle.parameters, name, parameter.into());
+        self
+    }
+
+    /// Add a credential.
+    pub fn credential<S: Into<String>, C: Into<Credential>>(
+        mut self,
+        name: S,
+        credential: C,
+    ) -> Self {
+        insert(&mut self.bundle.credentials, name, credential.into());
+        self
+    }
+
+    /// Add an output.
+    pub fn output<S: Into<String>, O: Into<Output>>(mut self, name: S, output: O) -> Self {
+        insert(&mut self.bundle.outputs, name, output.into());
+        self
+    }
+
+    /// Declare that a runtime must support the extension with the given key.
+    pub fn required_extension<S: Into<String>>(mut self, key: S) -> Self {
+        self.bundle
+            .required_extensions
+            .get_or_insert_with(Vec::new)
+            .push(key.into());
+        self
+    }
+
+    /// Add custom data under the given key.
+    pub fn custom<S: Into<String>>(mut self, key: S, value: Value) -> Self {
+        insert(&mut self.bundle.custom, key, value);
+        self
+    }
+
+    /// Validate and return the bundle.
+    pub fn build(self) -> Result<Bundle, Vec<ValidationError>> {
+        self.bundle.validate()?;
+        Ok(self.bundle)
+    }
+}
+
+fn insert<S: Into<String>, T>(map: &mut Option<BTreeMap<String, T>>, key: S, value: T) {
+    map.get_or_insert_with(BTreeMap::new)
+        .insert(key.into(), value);
+}
+
+/// ParameterBuilder constructs a `Parameter`.
+#[derive(Debug)]
+pub struct ParameterBuilder {
+    parameter: Parameter,
+}
+
+impl ParameterBuilder {
+    /// Start a parameter with no destination.
+    pub fn new() -> Self {
+        ParameterBuilder {
+            parameter: Parameter {
+                apply_to: None,
+                definition: None,
+                description: None,
+                destination: Destination {
+                    env: None,
+                    path: None,
+                    unknown_fields: BTreeMap::new(),
+                },
+                required: None,
+                unknown_fields: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Restrict the parameter to an action. May be called more than once.
+    pub fn apply_to<A: Into<BundleAction>>(mut self, action: A) -> Self {
+        self.parameter
+            .apply_to
+            .get_or_insert_with(Vec::new)
+            .push(action.into());
+        self
+    }
+
+    /// Set the name of the definition describing the parameter.
+    pub fn definition<S: Into<String>>(mut self, definition: S) -> Self {
+        self.parameter.definition = Some(definition.into());
+        self
+    }
+
+    /// Set the description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.parameter.description = Some(description.into());
+        self
+    }
+
+    /// Inject the parameter into an environment variable.
+    pub fn env<S: Into<String>>(mut self, env: S) -> Self {
+        self.parameter.destination.env = Some(env.into());
+        self
+    }
+
+    /// Inject the parameter into a file.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.parameter.destination.path = Some(path.into());
+        self
+    }
+
+    /// Set whether the parameter must be supplied.
+    pub fn required(mut self, required: bool) -> Self {
+        self.parameter.required = Some(required);
+        self
+    }
+
+    /// Return the parameter.
+    pub fn build(self) -> Parameter {
+        self.parameter
+    }
+}
+
+impl Default for ParameterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<ParameterBuilder> for Parameter {
+    fn from(builder: ParameterBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// CredentialBuilder constructs a `Credential`.
+#[derive(Debug)]
+pub struct CredentialBuilder {
+    credential: Credential,
+}
+
+impl CredentialBuilder {
+    /// Start a credential with no destination.
+    pub fn new() -> Self {
+        CredentialBuilder {
+            credential: Credential {
+                apply_to: None,
+                description: None,
+                env: None,
+                path: None,
+                required: None,
+                unknown_fields: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Restrict the credential to an action. May be called more than once.
+    pub fn apply_to<A: Into<BundleAction>>(mut self, action: A) -> Self {
+        self.credential
+            .apply_to
+            .get_or_insert_with(Vec::new)
+            .push(action.into());
+        self
+    }
+
+    /// Set the description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.credential.description = Some(description.into());
+        self
+    }
+
+    /// Inject the credential into an environment variable.
+    pub fn env<S: Into<String>>(mut self, env: S) -> Self {
+        self.credential.env = Some(env.into());
+        self
+    }
+
+    /// Inject the credential into a file.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.credential.path = Some(path.into());
+        self
+    }
+
+    /// Set whether the credential must be supplied.
+    pub fn required(mut self, required: bool) -> Self {
+        self.credential.required = Some(required);
+        self
+    }
+
+    /// Return the credential.
+    pub fn build(self) -> Credential {
+        self.credential
+    }
+}
+
+impl Default for CredentialBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<CredentialBuilder> for Credential {
+    fn from(builder: CredentialBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// OutputBuilder constructs an `Output`.
+#[derive(Debug)]
+pub struct OutputBuilder {
+    output: Output,
+}
+
+impl OutputBuilder {
+    /// Start an output described by the named definition.
+    pub fn new<S: Into<String>>(definition: S) -> Self {
+        OutputBuilder {
+            output: Output {
+                apply_to: None,
+                definition: definition.into(),
+                description: None,
+                path: None,
+                unknown_fields: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Restrict the output to an action. May be called more than once.
+    pub fn apply_to<A: Into<BundleAction>>(mut self, action: A) -> Self {
+        self.output
+            .apply_to
+            .get_or_insert_with(Vec::new)
+            .push(action.into());
+        self
+    }
+
+    /// Set the description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.output.description = Some(description.into());
+        self
+    }
+
+    /// Set the path inside the invocation image where the output is written.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.output.path = Some(path.into());
+        self
+    }
+
+    /// Return the output.
+    pub fn build(self) -> Output {
+        self.output
+    }
+}
+
+impl From<OutputBuilder> for Output {
+    fn from(builder: OutputBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// InvocationImageBuilder constructs an `InvocationImage`.
+#[derive(Debug)]
+pub struct InvocationImageBuilder {
+    image: InvocationImage,
+}
+
+impl InvocationImageBuilder {
+    /// Start an invocation image from a resolvable image reference.
+    ///
+    /// The image type defaults to `oci`.
+    pub fn new<S: Into<String>>(image: S) -> Self {
+        InvocationImageBuilder {
+            image: InvocationImage {
+                content_digest: None,
+                image: image.into(),
+                image_type: Some("oci".to_string()),
+                media_type: None,
+                original_image: None,
+                size: None,
+                labels: None,
+                unknown_fields: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Set the content digest.
+    pub fn content_digest(mut self, digest: Digest) -> Self {
+        self.image.content_digest = Some(digest.to_string());
+        self
+    }
+
+    /// Set the image type.
+    pub fn image_type<S: Into<String>>(mut self, image_type: S) -> Self {
+        self.image.image_type = Some(image_type.into());
+        self
+    }
+
+    /// Set the media type.
+    pub fn media_type<S: Into<String>>(mut self, media_type: S) -> Self {
+        self.image.media_type = Some(media_type.into());
+        self
+    }
+
+    /// Record the image reference that the image was copied from.
+    pub fn original_image<S: Into<String>>(mut self, original_image: S) -> Self {
+        self.image.original_image = Some(original_image.into());
+        self
+    }
+
+    /// Set the size in bytes.
+    pub fn size(mut self, size: i64) -> Self {
+        self.image.size = Some(size);
+        self
+    }
+
+    /// Add a label.
+    pub fn label<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        insert(&mut self.image.labels, key, value.into());
+        self
+    }
+
+    /// Return the invocation image.
+    pub fn build(self) -> InvocationImage {
+        self.image
+    }
+}
+
+impl From<InvocationImageBuilder> for InvocationImage {
+    fn from(builder: InvocationImageBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// ImageBuilder constructs an `Image`.
+#[derive(Debug)]
+pub struct ImageBuilder {
+    image: Image,
+}
+
+impl ImageBuilder {
+    /// Start an image from a resolvable image reference.
+    ///
+    /// The image type defaults to `oci`.
+    pub fn new<S: Into<String>>(image: S) -> Self {
+        ImageBuilder {
+            image: Image {
+                description: None,
+                content_digest: None,
+                image: image.into(),
+                image_type: Some("oci".to_string()),
+                media_type: None,
+                original_image: None,
+                platform: None,
+                size: None,
+                labels: None,
+                unknown_fields: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// Set the description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.image.description = Some(description.into());
+        self
+    }
+
+    /// Set the content digest.
+    pub fn content_digest(mut self, digest: Digest) -> Self {
+        self.image.content_digest = Some(digest.to_string());
+        self
+    }
+
+    /// Set the image type.
+    pub fn image_type<S: Into<String>>(mut self, image_type: S) -> Self {
+        self.image.image_type = Some(image_type.into());
+        self
+    }
+
+    /// Set the media type.
+    pub fn media_type<S: Into<String>>(mut self, media_type: S) -> Self {
+        self.image.media_type = Some(media_type.into());
+        self
+    }
+
+    /// Record the image reference that the image was copied from.
+    pub fn original_image<S: Into<String>>(mut self, original_image: S) -> Self {
+        self.image.original_image = Some(original_image.into());
+        self
+    }
+
+    /// Set the platform the image may be deployed on.
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.image.platform = Some(platform);
+        self
+    }
+
+    /// Set the size in bytes.
+    pub fn size(mut self, size: i64) -> Self {
+        self.image.size = Some(size);
+        self
+    }
+
+    /// Add a label.
+    pub fn label<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        insert(&mut self.image.labels, key, value.into());
+        self
+    }
+
+    /// Return the image.
+    pub fn build(self) -> Image {
+        self.image
+    }
+}
+
+impl From<ImageBuilder> for Image {
+    fn from(builder: ImageBuilder) -> Self {
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::validation::ValidationErrorKind;
+
+    #[test]
+    fn test_build_bundle() {
+        let bun = BundleBuilder::new("aristotle", Version::new(1, 2, 3))
+            .description("a philosopher")
+            .license("MIT")
+            .keyword("greek")
+            .keyword("logic")
+            .maintainer(Maintainer {
+                email: None,
+                name: "Plato".to_string(),
+                url: None,
+                unknown_fields: BTreeMap::new(),
+            })
+            .invocation_image(
+                InvocationImageBuilder::new("technosophos/aristotle:1.2.3")
+                    .size(1024)
+                    .label("org", "lyceum"),
+            )
+            .image(
+                "web",
+                ImageBuilder::new("nginx:latest").description("frontend"),
+            )
+            .action(
+                "status",
+                Action {
+                    description: None,
+                    modifies: false,
+                    stateless: true,
+                    unknown_fields: BTreeMap::new(),
+                },
+            )
+            .definition(
+                "port",
+                serde_json::from_str(r#"{"type": "integer"}"#).expect("schema"),
+            )
+            .parameter(
+                "port",
+                ParameterBuilder::new()
+                    .env("PORT")
+                    .definition("port")
+                    .apply_to("install")
+                    .apply_to("status")
+                    .required(true),
+            )
+            .credential(
+                "kubeconfig",
+                CredentialBuilder::new().path("/root/.kube/config"),
+            )
+            .output(
+                "address",
+                OutputBuilder::new("port")
+                    .apply_to("install")
+                    .path("/cnab/app/outputs/address"),
+            )
+            .custom("com.example.praxis", serde_json::json!({"techne": true}))
+            .required_extension("com.example.praxis")
+            .build()
+            .expect("valid bundle");
+
+        assert_eq!(bun.name, "aristotle");
+        assert_eq!(bun.version, Version::new(1, 2, 3));
+        assert_eq!(bun.schema_version, CNAB_SCHEMA_VERSION);
+        assert_eq!(
+            bun.keywords,
+            Some(vec!["greek".to_string(), "logic".to_string()])
+        );
+        assert_eq!(bun.invocation_images[0].image_type, Some("oci".to_string()));
+        assert_eq!(bun.invocation_images[0].size, Some(1024));
+
+        let port = &bun.parameters.as_ref().expect("parameters")["port"];
+        assert_eq!(port.destination.env, Some("PORT".to_string()));
+        assert_eq!(
+            port.apply_to,
+            Some(vec![BundleAction::Install, BundleAction::from("status")])
+        );
+        assert_eq!(port.required, Some(true));
+
+        let kubeconfig = &bun.credentials.as_ref().expect("credentials")["kubeconfig"];
+        assert_eq!(kubeconfig.path, Some(PathBuf::from("/root/.kube/config")));
+        assert_eq!(
+            bun.required_extensions,
+            Some(vec!["com.example.praxis".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_build_invalid_bundle() {
+        let errors = BundleBuilder::new("aristotle", Version::new(1, 0, 0))
+            .parameter("port", ParameterBuilder::new().definition("port"))
+            .build()
+            .expect_err("invalid bundle");
+
+        let kinds: Vec<ValidationErrorKind> = errors.into_iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ValidationErrorKind::MissingInvocationImages,
+                ValidationErrorKind::UndefinedDefinition("port".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+
+/// Serialize a value as [Canonical JSON](http://wiki.laptop.org/go/Canonical_JSON).
+///
+/// CNAB Core 101 requires `bundle.json` to be written in this form: object keys are
+/// sorted by their byte values, no insignificant whitespace is emitted, and only `"`
+/// and `\` are escaped inside strings.
+///
+/// ```
+/// let value = serde_json::json!({"b": "two\nlines", "a": [1, true, null]});
+/// let json = libcnab::to_canonical_json(&value).unwrap();
+/// assert_eq!(json, "{\"a\":[1,true,null],\"b\":\"two\nlines\"}");
+/// ```
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<String, CanonicalJSONError> {
+    let mut buf = Vec::new();
+    write_canonical_json(&mut buf, value)?;
+    // Every byte written came from a Rust string or an ASCII literal.
+    Ok(String::from_utf8(buf).expect("canonical JSON is valid UTF-8"))
+}
+
+/// Serialize a value as Canonical JSON into any type implementing `Write`.
+pub fn write_canonical_json<W: Write, T: Serialize + ?Sized>(
+    mut writer: W,
+    value: &T,
+) -> Result<(), CanonicalJSONError> {
+    let value = serde_json::to_value(value)?;
+    write_value(&mut writer, &value)
+}
+
+fn write_value<W: Write>(writer: &mut W, value: &Value) -> Result<(), CanonicalJSONError> {
+    match value {
+        Value::Null => writer.write_all(b"null")?,
+        Value::Bool(true) => writer.write_all(b"true")?,
+        Value::Bool(false) => writer.write_all(b"false")?,
+        Value::Number(n) => {
+            // Canonical JSON only has integers.
+            if n.is_f64() {
+                return Err(CanonicalJSONError::FloatingPointNumber(n.to_string()));
+            }
+            write!(writer, "{}", n)?;
+        }
+        Value::String(s) => write_string(writer, s)?,
+        Value::Array(items) => {
+            writer.write_all(b"[")?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write_value(writer, item)?;
+            }
+            writer.write_all(b"]")?;
+        }
+        Value::Object(map) => {
+            // Sort explicitly rather than relying on the map's iteration order, which
+            // changes if serde_json's `preserve_order` feature is enabled.
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            writer.write_all(b"{")?;
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write_string(writer, key)?;
+                writer.write_all(b":")?;
+                write_value(writer, item)?;
+            }
+            writer.write_all(b"}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_string<W: Write>(writer: &mut W, s: &str) -> std::io::Result<()> {
+    writer.write_all(b"\"")?;
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    for (i, b) in bytes.iter().enumerate() {
+        if *b == b'"' || *b == b'\\' {
+            writer.write_all(&bytes[start..i])?;
+            writer.write_all(&[b'\\', *b])?;
+            start = i + 1;
+        }
+    }
+    writer.write_all(&bytes[start..])?;
+    writer.write_all(b"\"")
+}
+
+/// Represents an error serializing a value as Canonical JSON
+#[derive(Debug)]
+pub enum CanonicalJSONError {
+    SerdeJSONError(serde_json::Error),
+    IoError(std::io::Error),
+    /// Canonical JSON cannot represent non-integer numbers.
+    FloatingPointNumber(String),
+}
+
+impl std::fmt::Display for CanonicalJSONError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CanonicalJSONError::SerdeJSONError(e) => write!(f, "{}", e),
+            CanonicalJSONError::IoError(e) => write!(f, "{}", e),
+            CanonicalJSONError::FloatingPointNumber(n) => write!(
+                f,
+                "floating point number {} cannot be represented in canonical JSON",
+                n
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CanonicalJSONError {}
+
+impl From<std::io::Error> for CanonicalJSONError {
+    fn from(error: std::io::Error) -> Self {
+        CanonicalJSONError::IoError(error)
+    }
+}
+
+impl From<serde_json::Error> for CanonicalJSONError {
+    fn from(error: serde_json::Error) -> Self {
+        CanonicalJSONError::SerdeJSONError(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical_sorts_keys() {
+        let json = to_canonical_json(&json!({
+            "zeta": 1,
+            "alpha": {"b": 2, "a": 1},
+            "Beta": []
+        }))
+        .expect("canonical json");
+        assert_eq!(json, r#"{"Beta":[],"alpha":{"a":1,"b":2},"zeta":1}"#);
+    }
+
+    #[test]
+    fn test_canonical_string_escaping() {
+        let json =
+            to_canonical_json(&json!("quote\" backslash\\ tab\t é")).expect("canonical json");
+        assert_eq!(json, "\"quote\\\" backslash\\\\ tab\t é\"");
+    }
+
+    #[test]
+    fn test_canonical_rejects_floats() {
+        let res = to_canonical_json(&json!({"half": 0.5}));
+        match res {
+            Err(CanonicalJSONError::FloatingPointNumber(n)) => assert_eq!(n, "0.5"),
+            other => panic!("expected a floating point error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_canonical_negative_integers() {
+        let json = to_canonical_json(&json!([-1, 0, 18446744073709551615u64])).expect("canonical");
+        assert_eq!(json, "[-1,0,18446744073709551615]");
+    }
+}
@@ -38,7 +38,7 @@ pub struct Claim {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Response {
-    action: String,
+    action: crate::action::BundleAction,
     message: Option<String>,
     status: Status,
 }
@@ -66,6 +66,7 @@ mod test {
                     "invocationImages": [],
                     "schemaVersion": "1.0.0",
                     "version": "1.0.0",
+                    "com.example.owner": "lyceum",
                     "custom": {
                         "com.example.praxis": {
                             "techne": true
@@ -94,5 +95,6 @@ mod test {
         .expect("Successfully parsed claim");
 
         assert_eq!(claim.result.status, Status::Success);
+        assert!(claim.bundle.unknown_fields.is_empty());
     }
 }
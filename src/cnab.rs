@@ -1,11 +1,19 @@
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use serde_json::error::Category;
+use serde_path_to_error::Segment;
+use std::cell::Cell;
 use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use crate::action::BundleAction;
+use crate::canonical::{self, CanonicalJSONError};
+use crate::schema::SchemaOrBool;
+use crate::validation::{pointer, ValidationError};
+
 /// Bundle implements a CNAB bundle descriptor
 ///
 /// Bundle descriptors describe the properties of a bundle, including which images
@@ -13,58 +21,79 @@ use std::str::FromStr;
 /// are any additional target actions that can be executed on this bundle.
 ///
 /// The fields here are in canonical order.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Bundle {
     /// The list of additional actions that this bundle can perform.
     ///
     /// 'install', 'upgrade', and 'uninstall' are default actions, but additional actions
     /// may be defined here.
-    pub actions: Option<BTreeMap<String, Action>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<BTreeMap<BundleAction, Action>>,
     /// The list of configurable credentials.
     ///
     /// Credentials are injected into the bundle's invocation image at startup time.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub credentials: Option<BTreeMap<String, Credential>>,
     /// This field allows for additional data to described in the bundle.
     ///
     /// This data should be stored in key/value pairs, where the value is undefined by
     /// the specification (but must be representable as JSON).
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub custom: Option<BTreeMap<String, serde_json::Value>>,
 
-    /// The JSON Schemata describing the parameters
-    ///
-    /// TODO: Should use a suitable Rust library as the target for this.
-    pub definitions: Option<BTreeMap<String, serde_json::Value>>,
+    /// The JSON Schemata describing the parameters and outputs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definitions: Option<BTreeMap<String, SchemaOrBool>>,
 
     /// description is a short description of this bundle
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// The list of images that comprise this bundle.
     ///
     /// Each image here is considered a constituent of the application described by this
     /// bundle.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub images: Option<BTreeMap<String, Image>>,
     /// The list of available bootstrapping images for this bundle
     ///
     /// Only one ought to be executed.
     pub invocation_images: Vec<InvocationImage>,
     /// A list of keywords describing this bundle
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub keywords: Option<Vec<String>>,
     /// The SPDX license identifier of this bundle
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub license: Option<String>,
     /// A list of maintainers responsible for this bundle
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub maintainers: Option<Vec<Maintainer>>,
     /// The name of the bundle
     pub name: String,
     /// The name/value pairs of outputs that this bundle produces.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub outputs: Option<BTreeMap<String, Output>>,
     /// The collection of parameters that can be passed into this bundle.
     ///
     /// Parameters can be injected into a bundle during startup time.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub parameters: Option<BTreeMap<String, Parameter>>,
+    /// The custom extensions that a runtime must support in order to run this bundle.
+    ///
+    /// Each is the key of an extension, such as `io.cnab.dependencies`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_extensions: Option<Vec<String>>,
     /// schema_version is the version of the CNAB specification used to describe this
     pub schema_version: String,
     /// version is the version of the bundle
     pub version: Version,
+    /// Fields that are not described by this struct, such as vendor extensions to the
+    /// specification.
+    ///
+    /// These, and the unknown fields of every nested struct, are only retained by lossless
+    /// deserialization. See `Bundle::from_json_lossless`.
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// Represents a bundle.
@@ -83,29 +112,208 @@ impl Bundle {
     }
 
     /// Deserialize a `Bundle` from any type implementing `Read`.
+    ///
+    /// Fields that this library does not model are discarded.
     pub fn from_json<R: Read>(reader: R) -> Result<Self, BundleParseError> {
-        let bundle = serde_json::from_reader(reader)?;
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let bundle = deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(bundle)
+    }
+
+    /// Deserialize a `Bundle` from any type implementing `Read`, keeping every field.
+    ///
+    /// Fields that this library does not model are kept in the `unknown_fields` of the
+    /// struct they appear in, and are written back when the bundle is serialized. A bundle
+    /// can therefore be edited without losing vendor-specific data, or changing its digest.
+    /// The `Deserialize` implementation, like `from_json`, discards them.
+    ///
+    /// ```
+    /// use libcnab::Bundle;
+    ///
+    /// let json = r#"{"com.example.color":"blue","invocationImages":[],"name":"aristotle","schemaVersion":"v1.0.0","version":"1.0.0"}"#;
+    /// let bundle = Bundle::from_json_lossless(json.as_bytes()).unwrap();
+    /// assert_eq!(bundle.unknown_fields["com.example.color"], "blue");
+    /// assert_eq!(bundle.to_canonical_json().unwrap(), json);
+    /// ```
+    pub fn from_json_lossless<R: Read>(reader: R) -> Result<Self, BundleParseError> {
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let bundle = deserialize_lossless(&mut deserializer)?;
+        deserializer.end()?;
         Ok(bundle)
     }
+
+    /// A convenience function to open and losslessly deserialize a `bundle.json` file.
+    pub fn from_file_lossless<P: AsRef<Path>>(path: P) -> Result<Self, BundleParseError> {
+        let file = File::open(path)?;
+        Self::from_json_lossless(file)
+    }
+
+    /// Serialize this bundle as [Canonical JSON](http://wiki.laptop.org/go/Canonical_JSON).
+    ///
+    /// This is the form required by the CNAB Core specification, and the form that
+    /// should be used whenever a bundle is signed or digested.
+    ///
+    /// ```
+    /// use libcnab::Bundle;
+    ///
+    /// let bundle: Bundle = r#"{
+    ///     "version": "1.0.0",
+    ///     "schemaVersion": "v1.0.0",
+    ///     "name": "aristotle",
+    ///     "invocationImages": []
+    /// }"#.parse().unwrap();
+    /// assert_eq!(
+    ///     bundle.to_canonical_json().unwrap(),
+    ///     r#"{"invocationImages":[],"name":"aristotle","schemaVersion":"v1.0.0","version":"1.0.0"}"#
+    /// );
+    /// ```
+    pub fn to_canonical_json(&self) -> Result<String, CanonicalJSONError> {
+        canonical::to_canonical_json(self)
+    }
+
+    /// Serialize this bundle as Canonical JSON into any type implementing `Write`.
+    pub fn write_canonical_json<W: Write>(&self, writer: W) -> Result<(), CanonicalJSONError> {
+        canonical::write_canonical_json(writer, self)
+    }
 }
 
 impl FromStr for Bundle {
-    type Err = serde_json::Error;
+    type Err = BundleParseError;
 
     fn from_str(json_data: &str) -> Result<Self, Self::Err> {
-        let bundle = serde_json::from_str(json_data)?;
-        Ok(bundle)
+        Self::from_json(json_data.as_bytes())
+    }
+}
+
+/// Deserialize a bundle, tracking the path to any value that cannot be deserialized.
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Bundle, BundleParseError>
+where
+    D: serde::Deserializer<'de, Error = serde_json::Error>,
+{
+    let bundle = serde_path_to_error::deserialize(deserializer)?;
+    Ok(bundle)
+}
+
+/// Deserialize a bundle like `deserialize`, keeping the fields it does not model.
+pub(crate) fn deserialize_lossless<'de, D>(deserializer: D) -> Result<Bundle, BundleParseError>
+where
+    D: serde::Deserializer<'de, Error = serde_json::Error>,
+{
+    let _lossless = Lossless::enable();
+    deserialize(deserializer)
+}
+
+thread_local! {
+    /// Whether `unknown_fields` are being kept by the deserialization on this thread.
+    static LOSSLESS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Keeps unknown fields until it is dropped, even if deserialization panics.
+struct Lossless(bool);
+
+impl Lossless {
+    fn enable() -> Self {
+        Lossless(LOSSLESS.with(|lossless| lossless.replace(true)))
+    }
+}
+
+impl Drop for Lossless {
+    fn drop(&mut self) {
+        LOSSLESS.with(|lossless| lossless.set(self.0));
+    }
+}
+
+/// Deserialize the fields a struct does not model, keeping them only when deserializing
+/// losslessly.
+fn unknown_fields<'de, D>(deserializer: D) -> Result<BTreeMap<String, serde_json::Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let fields = BTreeMap::deserialize(deserializer)?;
+    if LOSSLESS.with(Cell::get) {
+        Ok(fields)
+    } else {
+        Ok(BTreeMap::new())
     }
 }
 
 /// Represents an error parsing a bundle descriptor
 ///
 /// This captures the various errors that may bubble up when a bundle descriptor
-/// fails to parse.
+/// fails to parse. Lines and columns start at 1, and are 0 when the document was not
+/// parsed from text.
 #[derive(Debug)]
 pub enum BundleParseError {
-    SerdeJSONError(serde_json::Error),
+    /// The document could not be read
     IoError(std::io::Error),
+    /// The document is not well-formed JSON
+    SyntaxError {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The document is JSON, but a value does not have the structure a bundle requires
+    SchemaError {
+        /// A JSON pointer to the offending value, such as `/parameters/port/destination`
+        pointer: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// Strict parsing found fields the specification does not define, at these JSON pointers
+    UnknownFields(Vec<String>),
+    /// The bundle is well-formed, but violates the rules of the specification. Only
+    /// `Bundle::from_json_validated` and `Bundle::from_file_validated` check for this.
+    SemanticError(Vec<ValidationError>),
+}
+
+impl std::fmt::Display for BundleParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BundleParseError::IoError(e) => write!(f, "cannot read bundle: {}", e),
+            BundleParseError::SyntaxError {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "invalid JSON at line {}, column {}: {}",
+                line, column, message
+            ),
+            BundleParseError::SchemaError {
+                pointer,
+                line,
+                column,
+                message,
+            } => {
+                write!(f, "invalid bundle")?;
+                if !pointer.is_empty() {
+                    write!(f, " at {}", pointer)?;
+                }
+                if *line > 0 {
+                    write!(f, " (line {}, column {})", line, column)?;
+                }
+                write!(f, ": {}", message)
+            }
+            BundleParseError::UnknownFields(pointers) => {
+                write!(f, "unknown fields: {}", pointers.join(", "))
+            }
+            BundleParseError::SemanticError(errors) => {
+                let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", messages.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for BundleParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BundleParseError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<std::io::Error> for BundleParseError {
@@ -114,48 +322,128 @@ impl From<std::io::Error> for BundleParseError {
     }
 }
 
+/// An error message without the position that serde_json and serde_yaml append to it,
+/// for errors that report the position separately.
+pub(crate) fn without_position(message: &str, line: usize, column: usize) -> String {
+    let suffix = format!(" at line {} column {}", line, column);
+    message.strip_suffix(&suffix).unwrap_or(message).to_string()
+}
+
 impl From<serde_json::Error> for BundleParseError {
     fn from(error: serde_json::Error) -> Self {
-        BundleParseError::SerdeJSONError(error)
+        let (line, column) = (error.line(), error.column());
+        let message = without_position(&error.to_string(), line, column);
+        match error.classify() {
+            Category::Io => BundleParseError::IoError(error.into()),
+            Category::Syntax | Category::Eof => BundleParseError::SyntaxError {
+                line,
+                column,
+                message,
+            },
+            Category::Data => BundleParseError::SchemaError {
+                pointer: String::new(),
+                line,
+                column,
+                message,
+            },
+        }
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for BundleParseError {
+    fn from(error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let segments = path_segments(error.path());
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        match BundleParseError::from(error.into_inner()) {
+            BundleParseError::SchemaError {
+                line,
+                column,
+                message,
+                ..
+            } => BundleParseError::SchemaError {
+                pointer: pointer(&segments),
+                line,
+                column,
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+/// The JSON pointer segments of a path recorded while deserializing.
+pub(crate) fn path_segments(path: &serde_path_to_error::Path) -> Vec<String> {
+    path.iter()
+        .filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.clone()),
+            Segment::Enum { .. } | Segment::Unknown => None,
+        })
+        .collect()
+}
+
+impl From<Vec<ValidationError>> for BundleParseError {
+    fn from(errors: Vec<ValidationError>) -> Self {
+        BundleParseError::SemanticError(errors)
     }
 }
 
 /// Maintainer describes a bundle maintainer.
 ///
 /// The name field is required, though the format of its value is unspecified.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Maintainer {
     /// The email address of the maintainer
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
     /// The name of the maintainer
     pub name: String,
     /// A URL with more information about the maintainer
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// Image describes a CNAB image.
 ///
 /// Both invocation images and regular images can be described using this object.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Image {
     /// A description of the purpose of this image
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// A digest to be used to verify the integrity of the image
     /// A cryptographic hash digest of the contents of the image that can be used to validate the image. This may be interpreted differently based on imageType
+    ///
+    /// This is kept as written. Use `content_digest()` to parse it.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content_digest: Option<String>,
     /// A resolvable reference to the image. This may be interpreted differently based on imageType, but the default is to treat this as an OCI image
     pub image: String,
     /// The type of image. If not specified, this is treated as an OCI Image (`oci`)
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub image_type: Option<String>,
     /// The media type of the image
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub media_type: Option<String>,
+    /// The image reference that `image` was copied from, such as when a bundle is relocated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_image: Option<String>,
     /// The platform this image may be deployed on
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub platform: Option<Platform>,
     /// The size in bytes of the image
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<i64>,
     /// Key/value pairs that used to specify identifying attributes of images
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub labels: Option<BTreeMap<String, String>>,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// InvocationImage describes a bootstrapping image for a CNAB bundle.
@@ -163,7 +451,7 @@ pub struct Image {
 /// In the final CNAB Core 1.0 spec, this is subtly different than the regular Image type.
 ///
 /// This conforms to the CNAB Core 1.0 specification
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InvocationImage {
     /// A digest to be used to verify the integrity of the image
@@ -171,47 +459,78 @@ pub struct InvocationImage {
     ///
     /// The specification requires this field _at installation time_, but not during development. Thus it is optional, and the runtime must validate whether
     /// the circumstances require a value here.
+    ///
+    /// This is kept as written. Use `content_digest()` to parse it.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content_digest: Option<String>,
     /// A resolvable reference to the image. This may be interpreted differently based on imageType, but the default is to treat this as an OCI image
     pub image: String,
     /// The type of image. If not specified, this is treated as an OCI Image (`oci`)
     ///
     /// The spec lists this field as required, but with a defined default. We interpret that to mean that if None, then `oci`.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub image_type: Option<String>,
     /// The media type of the image
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub media_type: Option<String>,
+    /// The image reference that `image` was copied from, such as when a bundle is relocated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_image: Option<String>,
     /// The size in bytes of the image
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<i64>,
     /// Key/value pairs that used to specify identifying attributes of images
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub labels: Option<BTreeMap<String, String>>,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// Platform defines a platform as a machine architecture plus and operating system
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Platform {
     /// The architecture
     ///
     /// Typical values are amd64, i386, and arm64
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub arch: Option<String>,
     /// The operating system.
     ///
     /// Typical values are darwin, windows, and linux
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub os: Option<String>,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// Credential describes a particular credential that may be injected into a bundle
 ///
 /// Satisfies the CNAB Core 1.0 specification
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Credential {
+    /// The actions to which this credential applies.
+    ///
+    /// If unset, this credential will be applied to all actions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apply_to: Option<Vec<BundleAction>>,
     /// The description of this credential
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// The name of the environment variable into which the value will be placed
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub env: Option<String>,
     /// The fully qualified path into which the value will be placed
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<PathBuf>,
     /// Indicates whether this credential must be supplied. None is interpreted as "Some(false)".
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub required: Option<bool>,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// Parameter describes a parameter that will be put into the invocation image
@@ -219,16 +538,22 @@ pub struct Credential {
 /// Paramters are injected into the invocation image at startup time
 ///
 /// Conforms to CNAB Core 1.0
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Parameter {
     /// The actions to which this parameter applies.
     ///
     /// If unset, this parameter will be applied to all actions.
-    pub apply_to: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apply_to: Option<Vec<BundleAction>>,
     /// The name of a definition that describes the schema structure of this parameter
+    ///
+    /// The specification requires it, but bundles that predate CNAB Core 1.0 may not have
+    /// one. `Bundle::validate` reports it when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub definition: Option<String>,
     /// Human readable description of what this parameter does
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// This describes the underlying type of the parameter (string, int...)
     /// The location where this parameter will be injected in the invocation image
@@ -236,16 +561,35 @@ pub struct Parameter {
     /// Indicate whether this parameter is required
     ///
     /// None is treated as Some<false>
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub required: Option<bool>,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl Credential {
+    /// Returns true if this credential applies to the given action.
+    pub fn applies_to(&self, action: &BundleAction) -> bool {
+        applies_to(&self.apply_to, action)
+    }
+}
+
+impl Parameter {
+    /// Returns true if this parameter applies to the given action.
+    pub fn applies_to(&self, action: &BundleAction) -> bool {
+        applies_to(&self.apply_to, action)
+    }
 }
 
 /// An Action is a custom action in an invocation image.
 ///
 /// For example, an invocation image may provide help text by creating a 'help'
 /// action that, when triggered, prints help text to STDOUT.
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Action {
     /// Describes what this action does
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// If true, this action modifies the deployment, and should be tracked as a release.
     #[serde(default)]
@@ -256,12 +600,16 @@ pub struct Action {
     /// or parameters.
     #[serde(default)]
     pub stateless: bool,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// Describe a parameter
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Metadata {
     /// A description of a parameter
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 }
 
@@ -271,26 +619,54 @@ pub struct Metadata {
 /// A parameter value can be placed into an environment variable (`env`) or a file at
 /// a particular location on the filesystem (`path`). This is a non-exclusive or, meaning
 /// that the same paramter can be written to both an env var and a path.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Destination {
     /// The name of the destination environment variable
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub env: Option<String>,
     /// The fully qualified path to the destination file
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<PathBuf>,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
 /// A value that is produced by running an invocation image
 ///
 /// Complies to CNAB Core 1.0
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Output {
     /// An optional exhaustive list of actions producing this output
-    pub apply_to: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apply_to: Option<Vec<BundleAction>>,
     /// The name of a definition that describes the schema structure of this output
     pub definition: String,
     /// Human-readable description of this output
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// The path inside of the invocation image where output will be written
+    ///
+    /// The specification requires it. `Bundle::validate` reports it when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<PathBuf>,
+    /// Fields that are not described by this struct
+    #[serde(flatten, deserialize_with = "unknown_fields")]
+    pub unknown_fields: BTreeMap<String, serde_json::Value>,
+}
+
+impl Output {
+    /// Returns true if this output is produced by the given action.
+    pub fn applies_to(&self, action: &BundleAction) -> bool {
+        applies_to(&self.apply_to, action)
+    }
+}
+
+/// An unset `applyTo` list applies to every action.
+fn applies_to(apply_to: &Option<Vec<BundleAction>>, action: &BundleAction) -> bool {
+    match apply_to {
+        Some(actions) => actions.contains(action),
+        None => true,
+    }
 }
@@ -0,0 +1,665 @@
+use semver::Version;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::cnab::{
+    self, Action, Bundle, Credential, Image, InvocationImage, Maintainer, Output, Parameter,
+};
+use crate::schema::SchemaOrBool;
+use crate::validation::pointer;
+
+/// The fields a bundle cannot be deserialized without.
+const REQUIRED_FIELDS: &[&str] = &["invocationImages", "name", "schemaVersion", "version"];
+
+/// Diagnostic parsing.
+impl Bundle {
+    /// Deserialize a `Bundle` from any type implementing `Read`, reporting every problem in
+    /// the document instead of stopping at the first.
+    ///
+    /// Each top-level field, and each entry of the bundle's maps and arrays, is
+    /// deserialized on its own. A value that fails is left out, with each problem in it
+    /// reported, and the rest of the document is still read. If every required field survives, the resulting
+    /// bundle is checked with `validate` and returned along with the diagnostics. A
+    /// document that is not well-formed JSON yields a single syntax diagnostic.
+    ///
+    /// Only an error reading from `reader` is returned as `Err`.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, DiagnosticKind};
+    ///
+    /// let json = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [{"image": "technosophos/aristotle:1.0.0"}],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0",
+    ///     "parameters": {
+    ///         "port": {"definition": "port", "destination": {"env": "PORT"}},
+    ///         "host": {"destination": "HOST"}
+    ///     }
+    /// }"#;
+    ///
+    /// let report = Bundle::diagnose_json(json.as_bytes()).unwrap();
+    /// assert_eq!(report.diagnostics.len(), 2);
+    /// assert_eq!(report.diagnostics[0].pointer, "/parameters/port/definition");
+    /// assert_eq!(report.diagnostics[0].kind, DiagnosticKind::Semantic);
+    /// assert_eq!(report.diagnostics[1].pointer, "/parameters/host/destination");
+    /// assert_eq!(report.diagnostics[1].kind, DiagnosticKind::Schema);
+    ///
+    /// let bundle = report.bundle.unwrap();
+    /// assert!(bundle.parameters.unwrap().contains_key("port"));
+    /// ```
+    pub fn diagnose_json<R: Read>(mut reader: R) -> Result<Diagnosis, std::io::Error> {
+        let mut json = String::new();
+        reader.read_to_string(&mut json)?;
+        Ok(diagnose(&json))
+    }
+
+    /// A convenience function to open and diagnose a `bundle.json` file.
+    pub fn diagnose_file<P: AsRef<Path>>(path: P) -> Result<Diagnosis, std::io::Error> {
+        let file = File::open(path)?;
+        Self::diagnose_json(file)
+    }
+}
+
+fn diagnose(json: &str) -> Diagnosis {
+    let mut diagnostics = Vec::new();
+    let document: Value = match serde_json::from_str(json) {
+        Ok(document) => document,
+        Err(e) => {
+            diagnostics.push(Diagnostic {
+                pointer: String::new(),
+                line: e.line(),
+                column: e.column(),
+                kind: DiagnosticKind::Syntax,
+                message: cnab::without_position(&e.to_string(), e.line(), e.column()),
+            });
+            return Diagnosis {
+                bundle: None,
+                diagnostics,
+            };
+        }
+    };
+
+    let mut fields = match document {
+        Value::Object(fields) => fields,
+        other => {
+            diagnostics.push(Diagnostic::new(
+                json,
+                String::new(),
+                DiagnosticKind::Schema,
+                format!("expected a bundle object, found {}", describe(&other)),
+            ));
+            return Diagnosis {
+                bundle: None,
+                diagnostics,
+            };
+        }
+    };
+
+    // The original index of each element left in the arrays that were pruned, by field.
+    let mut kept = BTreeMap::new();
+    let keys: Vec<String> = fields.keys().cloned().collect();
+    for key in keys {
+        let mut value = fields.remove(&key).unwrap_or(Value::Null);
+        let path = vec![key.clone()];
+        let keep = match key.as_str() {
+            "name" | "description" | "license" | "schemaVersion" => {
+                check::<String>(json, &path, &value, &mut diagnostics)
+            }
+            "version" => check::<Version>(json, &path, &value, &mut diagnostics),
+            "keywords" | "requiredExtensions" => {
+                prune_elements::<String>(json, &path, &mut value, &mut kept, &mut diagnostics)
+            }
+            "maintainers" => {
+                prune_elements::<Maintainer>(json, &path, &mut value, &mut kept, &mut diagnostics)
+            }
+            "invocationImages" => prune_elements::<InvocationImage>(
+                json,
+                &path,
+                &mut value,
+                &mut kept,
+                &mut diagnostics,
+            ),
+            "images" => prune_entries::<Image>(json, &path, &mut value, &mut diagnostics),
+            "actions" => prune_entries::<Action>(json, &path, &mut value, &mut diagnostics),
+            "credentials" => prune_entries::<Credential>(json, &path, &mut value, &mut diagnostics),
+            "parameters" => prune_entries::<Parameter>(json, &path, &mut value, &mut diagnostics),
+            "outputs" => prune_entries::<Output>(json, &path, &mut value, &mut diagnostics),
+            "definitions" => {
+                prune_entries::<SchemaOrBool>(json, &path, &mut value, &mut diagnostics)
+            }
+            "custom" => check::<Map<String, Value>>(json, &path, &value, &mut diagnostics),
+            // Fields this crate does not model cannot be wrong.
+            _ => true,
+        };
+        if keep {
+            fields.insert(key, value);
+        }
+    }
+
+    let mut complete = true;
+    for field in REQUIRED_FIELDS {
+        if !fields.contains_key(*field) {
+            complete = false;
+            let path = pointer(&[field]);
+            // A field that failed to deserialize has already been reported.
+            if !diagnostics.iter().any(|d| d.pointer == path) {
+                diagnostics.push(Diagnostic::new(
+                    json,
+                    path,
+                    DiagnosticKind::Schema,
+                    format!("missing field `{}`", field),
+                ));
+            }
+        }
+    }
+
+    let bundle = if complete {
+        match cnab::deserialize(Value::Object(fields)) {
+            Ok(bundle) => {
+                if let Err(errors) = bundle.validate() {
+                    for error in errors {
+                        diagnostics.push(Diagnostic::new(
+                            json,
+                            original_pointer(error.pointer, &kept),
+                            DiagnosticKind::Semantic,
+                            error.kind.to_string(),
+                        ));
+                    }
+                }
+                Some(bundle)
+            }
+            // Every field has been checked, so this is not expected. Report it regardless.
+            Err(e) => {
+                diagnostics.push(Diagnostic::new(
+                    json,
+                    String::new(),
+                    DiagnosticKind::Schema,
+                    e.to_string(),
+                ));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+
+    Diagnosis {
+        bundle,
+        diagnostics,
+    }
+}
+
+/// Report every value that stops `value` deserializing as a `T`, and whether there were
+/// none.
+///
+/// Deserialization stops at the first error, so the offending value is removed and the
+/// rest deserialized again. An error at a value that something was removed from, such as
+/// a missing required field, is caused by the removal; it is not reported, and that value
+/// is removed in turn.
+fn check<T: DeserializeOwned>(
+    json: &str,
+    path: &[String],
+    value: &Value,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> bool {
+    let mut value = value.clone();
+    let mut removed: Vec<Vec<String>> = Vec::new();
+    loop {
+        let e = match serde_path_to_error::deserialize::<_, T>(&value) {
+            Ok(_) => return removed.is_empty(),
+            Err(e) => e,
+        };
+        let at = cnab::path_segments(e.path());
+        let caused = removed
+            .iter()
+            .any(|r| r.len() == at.len() + 1 && r.starts_with(&at));
+        if !caused {
+            let mut path = path.to_vec();
+            path.extend(at.iter().cloned());
+            let segments: Vec<&str> = path.iter().map(String::as_str).collect();
+            let inner = e.inner();
+            diagnostics.push(Diagnostic::new(
+                json,
+                pointer(&segments),
+                DiagnosticKind::Schema,
+                cnab::without_position(&inner.to_string(), inner.line(), inner.column()),
+            ));
+        }
+        match remove(&mut value, &at) {
+            Some(at) => removed.push(at),
+            None => return false,
+        }
+    }
+}
+
+/// Remove the value at `path` from `value`, returning the path of what was removed.
+///
+/// Removing an element from an array would move the elements after it, so the whole
+/// array is removed instead. Nothing is removed when `path` is empty.
+fn remove(value: &mut Value, path: &[String]) -> Option<Vec<String>> {
+    let (last, parents) = path.split_last()?;
+    let segments: Vec<&str> = parents.iter().map(String::as_str).collect();
+    match value.pointer_mut(&pointer(&segments))? {
+        Value::Object(entries) => entries.remove(last).map(|_| path.to_vec()),
+        Value::Array(_) => remove(value, parents),
+        _ => None,
+    }
+}
+
+/// Remove the elements of an array that do not deserialize as a `T`, recording the
+/// original indexes of the rest in `kept`.
+fn prune_elements<T: DeserializeOwned>(
+    json: &str,
+    path: &[String],
+    value: &mut Value,
+    kept: &mut BTreeMap<String, Vec<usize>>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> bool {
+    let elements = match value {
+        Value::Array(elements) => elements,
+        _ => return check::<Vec<T>>(json, path, value, diagnostics),
+    };
+    let mut indexes = Vec::new();
+    let mut i = 0;
+    elements.retain(|element| {
+        let mut path = path.to_vec();
+        path.push(i.to_string());
+        let keep = check::<T>(json, &path, element, diagnostics);
+        if keep {
+            indexes.push(i);
+        }
+        i += 1;
+        keep
+    });
+    kept.insert(path.join("/"), indexes);
+    true
+}
+
+/// Map a pointer into the pruned bundle back to the element of the original document
+/// that it refers to.
+fn original_pointer(pointer: String, kept: &BTreeMap<String, Vec<usize>>) -> String {
+    let mut segments: Vec<String> = pointer.splitn(4, '/').map(String::from).collect();
+    let original = match segments.as_slice() {
+        [_, field, index, ..] => kept
+            .get(field)
+            .and_then(|indexes| indexes.get(index.parse::<usize>().ok()?)),
+        _ => None,
+    };
+    match original {
+        Some(original) => {
+            segments[2] = original.to_string();
+            segments.join("/")
+        }
+        None => pointer,
+    }
+}
+
+/// Remove the entries of an object that do not deserialize as a `T`.
+fn prune_entries<T: DeserializeOwned>(
+    json: &str,
+    path: &[String],
+    value: &mut Value,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> bool {
+    let entries = match value {
+        Value::Object(entries) => entries,
+        _ => return check::<Map<String, Value>>(json, path, value, diagnostics),
+    };
+    let keys: Vec<String> = entries.keys().cloned().collect();
+    for key in keys {
+        let mut path = path.to_vec();
+        path.push(key.clone());
+        if !check::<T>(json, &path, &entries[&key], diagnostics) {
+            entries.remove(&key);
+        }
+    }
+    true
+}
+
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// The line and column at which the value at `pointer` starts in the JSON text `json`.
+///
+/// If no value exists at `pointer`, the position of the deepest value on the way to it is
+/// returned. `json` must be well-formed.
+fn locate(json: &str, pointer: &str) -> (usize, usize) {
+    let segments: Vec<String> = pointer
+        .split('/')
+        .skip(1)
+        .map(|s| s.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    let bytes = json.as_bytes();
+    let mut at = skip_whitespace(bytes, 0);
+    for segment in &segments {
+        match child(json, at, segment) {
+            Some(start) => at = start,
+            None => break,
+        }
+    }
+    position(json, at)
+}
+
+/// The offset of the value named `segment` within the object or array starting at `at`.
+fn child(json: &str, at: usize, segment: &str) -> Option<usize> {
+    let bytes = json.as_bytes();
+    match bytes.get(at)? {
+        b'{' => {
+            let mut i = skip_whitespace(bytes, at + 1);
+            while bytes.get(i) == Some(&b'"') {
+                let end = skip_string(bytes, i);
+                let key: String = serde_json::from_str(&json[i..end]).ok()?;
+                i = skip_whitespace(bytes, end);
+                i = skip_whitespace(bytes, i + 1); // the colon
+                if key == segment {
+                    return Some(i);
+                }
+                i = skip_whitespace(bytes, skip_value(bytes, i));
+                if bytes.get(i) == Some(&b',') {
+                    i = skip_whitespace(bytes, i + 1);
+                }
+            }
+            None
+        }
+        b'[' => {
+            let index: usize = segment.parse().ok()?;
+            let mut i = skip_whitespace(bytes, at + 1);
+            for _ in 0..index {
+                if bytes.get(i) == Some(&b']') {
+                    return None;
+                }
+                i = skip_whitespace(bytes, skip_value(bytes, i));
+                if bytes.get(i) == Some(&b',') {
+                    i = skip_whitespace(bytes, i + 1);
+                }
+            }
+            match bytes.get(i) {
+                Some(b']') | None => None,
+                Some(_) => Some(i),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// The offset just past the string starting at `i`.
+fn skip_string(bytes: &[u8], mut i: usize) -> usize {
+    i += 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    i
+}
+
+/// The offset just past the value starting at `i`.
+fn skip_value(bytes: &[u8], mut i: usize) -> usize {
+    let mut depth = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i = skip_string(bytes, i);
+                if depth == 0 {
+                    return i;
+                }
+                continue;
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth == 0 => return i,
+            b'}' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            b',' if depth == 0 => return i,
+            b if b.is_ascii_whitespace() && depth == 0 => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+/// The one-based line and column of byte offset `at`.
+fn position(json: &str, at: usize) -> (usize, usize) {
+    let before = &json[..at.min(json.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// Diagnosis is the result of diagnosing a bundle document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    /// The bundle, without the values that could not be deserialized. This is `None` if the
+    /// document is not well-formed or lacks a required field.
+    pub bundle: Option<Bundle>,
+    /// Every problem found, in the order in which they appear in the document
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnosis {
+    /// Returns true if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Diagnostic describes one problem in a bundle document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// A JSON pointer to the offending value, such as `/parameters/port/destination`
+    pub pointer: String,
+    /// The line at which the offending value starts
+    pub line: usize,
+    /// The column at which the offending value starts
+    pub column: usize,
+    /// The kind of problem
+    pub kind: DiagnosticKind,
+    /// A description of the problem
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Describe a problem with the value at `pointer`, locating it in the JSON text `json`.
+    fn new(json: &str, pointer: String, kind: DiagnosticKind, message: String) -> Self {
+        let (line, column) = locate(json, &pointer);
+        Diagnostic {
+            pointer,
+            line,
+            column,
+            kind,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        if !self.pointer.is_empty() {
+            write!(f, "{}: ", self.pointer)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The kinds of problem that diagnosing a bundle document reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// The document is not well-formed JSON
+    Syntax,
+    /// A value does not have the structure a bundle requires
+    Schema,
+    /// The bundle violates the rules of the specification
+    Semantic,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diagnose_reports_every_problem() {
+        let json = r#"{
+    "name": "aristotle",
+    "invocationImages": [
+        {"image": "technosophos/aristotle:1.0.0"},
+        {"image": "technosophos/aristotle:1.0.0", "size": "big"}
+    ],
+    "schemaVersion": "v1.0.0",
+    "version": "1.0",
+    "keywords": ["philosophy", 42],
+    "parameters": {
+        "port": {"definition": "port", "destination": {"env": "PORT"}},
+        "host": {"destination": "HOST"}
+    },
+    "actions": {"install": {"modifies": true}}
+}"#;
+
+        let report = Bundle::diagnose_json(json.as_bytes()).expect("diagnosis");
+        let found: Vec<(&str, usize, DiagnosticKind)> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.pointer.as_str(), d.line, d.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("/invocationImages/1/size", 5, DiagnosticKind::Schema),
+                ("/version", 8, DiagnosticKind::Schema),
+                ("/keywords/1", 9, DiagnosticKind::Schema),
+                ("/parameters/host/destination", 12, DiagnosticKind::Schema),
+            ]
+        );
+        // A required field failed, so no bundle can be built.
+        assert!(report.bundle.is_none());
+    }
+
+    #[test]
+    fn test_diagnose_returns_partial_bundle() {
+        let json = r#"{
+    "name": "aristotle",
+    "invocationImages": [
+        {"image": "technosophos/aristotle:1.0.0"},
+        {"image": "technosophos/aristotle:1.0.0", "size": "big"}
+    ],
+    "schemaVersion": "v1.0.0",
+    "version": "1.0.0",
+    "parameters": {
+        "port": {"definition": "port", "destination": {"env": "PORT"}},
+        "host": {"destination": "HOST"}
+    },
+    "actions": {"install": {"modifies": true}}
+}"#;
+
+        let report = Bundle::diagnose_json(json.as_bytes()).expect("diagnosis");
+        let found: Vec<(&str, usize, usize, DiagnosticKind)> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.pointer.as_str(), d.line, d.column, d.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("/invocationImages/1/size", 5, 59, DiagnosticKind::Schema),
+                (
+                    "/parameters/port/definition",
+                    10,
+                    32,
+                    DiagnosticKind::Semantic
+                ),
+                (
+                    "/parameters/host/destination",
+                    11,
+                    33,
+                    DiagnosticKind::Schema
+                ),
+                ("/actions/install", 13, 28, DiagnosticKind::Semantic),
+            ]
+        );
+
+        let bundle = report.bundle.expect("partial bundle");
+        assert_eq!(bundle.invocation_images.len(), 1);
+        let parameters = bundle.parameters.expect("parameters");
+        assert!(parameters.contains_key("port"));
+        assert!(!parameters.contains_key("host"));
+    }
+
+    #[test]
+    fn test_diagnose_pruned_elements() {
+        let json = r#"{
+    "name": "aristotle",
+    "invocationImages": [
+        {"image": 42, "size": "big"},
+        {"image": "technosophos/aristotle:1.0.0", "contentDigest": "sha256:nope"}
+    ],
+    "schemaVersion": "v1.0.0",
+    "version": "1.0.0"
+}"#;
+
+        let report = Bundle::diagnose_json(json.as_bytes()).expect("diagnosis");
+        let found: Vec<(&str, usize, DiagnosticKind)> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.pointer.as_str(), d.line, d.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("/invocationImages/0/image", 4, DiagnosticKind::Schema),
+                ("/invocationImages/0/size", 4, DiagnosticKind::Schema),
+                (
+                    "/invocationImages/1/contentDigest",
+                    5,
+                    DiagnosticKind::Semantic
+                ),
+            ]
+        );
+        assert_eq!(report.bundle.expect("bundle").invocation_images.len(), 1);
+    }
+
+    #[test]
+    fn test_diagnose_syntax_error() {
+        let report = Bundle::diagnose_json("{\n  \"name\": }".as_bytes()).expect("diagnosis");
+        assert!(report.bundle.is_none());
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].kind, DiagnosticKind::Syntax);
+        assert_eq!(report.diagnostics[0].line, 2);
+    }
+
+    #[test]
+    fn test_locate() {
+        let json = "{\"a\": [1, {\"b~/\": \"x\"}],\n \"c\": {}}";
+        assert_eq!(locate(json, ""), (1, 1));
+        assert_eq!(locate(json, "/a/1/b~0~1"), (1, 19));
+        assert_eq!(locate(json, "/c"), (2, 7));
+        assert_eq!(locate(json, "/c/missing"), (2, 7));
+    }
+}
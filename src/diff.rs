@@ -0,0 +1,467 @@
+use std::collections::BTreeMap;
+
+use crate::cnab::Bundle;
+use crate::schema::{InstanceType, SchemaOrBool, SchemaType};
+
+/// Every JSON type, for comparing the types two schemata accept.
+const INSTANCE_TYPES: [InstanceType; 7] = [
+    InstanceType::Array,
+    InstanceType::Boolean,
+    InstanceType::Integer,
+    InstanceType::Null,
+    InstanceType::Number,
+    InstanceType::Object,
+    InstanceType::String,
+];
+
+/// Bundle comparison.
+impl Bundle {
+    /// Compare this bundle with a newer version of it.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, BreakingChange};
+    ///
+    /// let old: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [{"image": "technosophos/aristotle:1.0.0"}],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0",
+    ///     "actions": {"status": {}}
+    /// }"#.parse().unwrap();
+    /// let new: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [{"image": "technosophos/aristotle:2.0.0"}],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "2.0.0"
+    /// }"#.parse().unwrap();
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.invocation_images.changed, vec!["0"]);
+    /// assert_eq!(diff.breaking, vec![BreakingChange::RemovedAction("status".to_string())]);
+    /// ```
+    pub fn diff(&self, new: &Bundle) -> BundleDiff {
+        let mut diff = BundleDiff {
+            parameters: diff_maps(&self.parameters, &new.parameters),
+            credentials: diff_maps(&self.credentials, &new.credentials),
+            outputs: diff_maps(&self.outputs, &new.outputs),
+            images: diff_maps(&self.images, &new.images),
+            invocation_images: SectionDiff::default(),
+            actions: diff_maps(&self.actions, &new.actions),
+            definitions: diff_maps(&self.definitions, &new.definitions),
+            breaking: Vec::new(),
+        };
+
+        let (old_len, new_len) = (self.invocation_images.len(), new.invocation_images.len());
+        for i in 0..old_len.max(new_len) {
+            match (self.invocation_images.get(i), new.invocation_images.get(i)) {
+                (Some(a), Some(b)) if a != b => diff.invocation_images.changed.push(i.to_string()),
+                (Some(_), None) => diff.invocation_images.removed.push(i.to_string()),
+                (None, Some(_)) => diff.invocation_images.added.push(i.to_string()),
+                _ => {}
+            }
+        }
+
+        diff.breaking = self.breaking_changes(new);
+        diff
+    }
+
+    fn breaking_changes(&self, new: &Bundle) -> Vec<BreakingChange> {
+        let mut breaking = Vec::new();
+
+        let no_parameters = BTreeMap::new();
+        let old_parameters = self.parameters.as_ref().unwrap_or(&no_parameters);
+        for name in old_parameters.keys() {
+            if !new.parameters.iter().any(|p| p.contains_key(name)) {
+                breaking.push(BreakingChange::RemovedParameter(name.clone()));
+            }
+        }
+        for (name, parameter) in new.parameters.iter().flatten() {
+            let was_required = old_parameters
+                .get(name)
+                .is_some_and(|p| p.required.unwrap_or(false));
+            if !was_required
+                && parameter.required.unwrap_or(false)
+                && new.parameter_default(parameter).is_none()
+            {
+                breaking.push(BreakingChange::RequiredParameter(name.clone()));
+            }
+        }
+
+        let no_credentials = BTreeMap::new();
+        let old_credentials = self.credentials.as_ref().unwrap_or(&no_credentials);
+        for (name, credential) in new.credentials.iter().flatten() {
+            let was_required = old_credentials
+                .get(name)
+                .is_some_and(|c| c.required.unwrap_or(false));
+            if !was_required && credential.required.unwrap_or(false) {
+                breaking.push(BreakingChange::RequiredCredential(name.clone()));
+            }
+        }
+
+        for name in self.outputs.iter().flat_map(BTreeMap::keys) {
+            if !new.outputs.iter().any(|o| o.contains_key(name)) {
+                breaking.push(BreakingChange::RemovedOutput(name.clone()));
+            }
+        }
+
+        for name in self.actions.iter().flat_map(BTreeMap::keys) {
+            if !new.actions.iter().any(|a| a.contains_key(name)) {
+                breaking.push(BreakingChange::RemovedAction(name.to_string()));
+            }
+        }
+
+        for (name, old) in self.definitions.iter().flatten() {
+            if let Some(new) = new.definitions.as_ref().and_then(|d| d.get(name)) {
+                if narrows_type(old, new) {
+                    breaking.push(BreakingChange::DefinitionTypeChanged {
+                        definition: name.clone(),
+                        from: schema_type(old),
+                        to: schema_type(new),
+                    });
+                }
+            }
+        }
+
+        for name in self.definitions.iter().flat_map(BTreeMap::keys) {
+            if !new.definitions.iter().any(|d| d.contains_key(name)) && new.references(name) {
+                breaking.push(BreakingChange::RemovedDefinition(name.clone()));
+            }
+        }
+
+        breaking
+    }
+
+    /// Returns true if a parameter or output uses the named definition.
+    fn references(&self, definition: &str) -> bool {
+        self.parameters
+            .iter()
+            .flat_map(BTreeMap::values)
+            .any(|p| p.definition.as_deref() == Some(definition))
+            || self
+                .outputs
+                .iter()
+                .flat_map(BTreeMap::values)
+                .any(|o| o.definition == definition)
+    }
+}
+
+/// Returns true if `new` rejects a type of value that `old` accepts.
+fn narrows_type(old: &SchemaOrBool, new: &SchemaOrBool) -> bool {
+    INSTANCE_TYPES
+        .iter()
+        .any(|&t| old.allows_type(t) && !new.allows_type(t))
+}
+
+/// The `type` of a definition. Boolean definitions have none.
+fn schema_type(definition: &SchemaOrBool) -> Option<SchemaType> {
+    definition.as_schema()?.schema_type.clone()
+}
+
+fn diff_maps<K: Ord + ToString, T: PartialEq>(
+    old: &Option<BTreeMap<K, T>>,
+    new: &Option<BTreeMap<K, T>>,
+) -> SectionDiff {
+    let empty = BTreeMap::new();
+    let old = old.as_ref().unwrap_or(&empty);
+    let new = new.as_ref().unwrap_or(&empty);
+
+    let mut diff = SectionDiff::default();
+    for (name, a) in old {
+        match new.get(name) {
+            Some(b) if a != b => diff.changed.push(name.to_string()),
+            Some(_) => {}
+            None => diff.removed.push(name.to_string()),
+        }
+    }
+    for name in new.keys() {
+        if !old.contains_key(name) {
+            diff.added.push(name.to_string());
+        }
+    }
+    diff
+}
+
+/// BundleDiff describes how one version of a bundle differs from another.
+///
+/// Map sections are keyed by name. Invocation images are keyed by their position in
+/// `invocationImages`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BundleDiff {
+    pub parameters: SectionDiff,
+    pub credentials: SectionDiff,
+    pub outputs: SectionDiff,
+    pub images: SectionDiff,
+    pub invocation_images: SectionDiff,
+    pub actions: SectionDiff,
+    pub definitions: SectionDiff,
+    /// The changes that can break existing installations or their users
+    pub breaking: Vec<BreakingChange>,
+}
+
+impl BundleDiff {
+    /// Returns true if the bundles do not differ in any section.
+    pub fn is_empty(&self) -> bool {
+        [
+            &self.parameters,
+            &self.credentials,
+            &self.outputs,
+            &self.images,
+            &self.invocation_images,
+            &self.actions,
+            &self.definitions,
+        ]
+        .iter()
+        .all(|section| section.is_empty())
+    }
+
+    /// Returns true if any change is breaking.
+    pub fn is_breaking(&self) -> bool {
+        !self.breaking.is_empty()
+    }
+}
+
+/// SectionDiff lists the entries of one section of a bundle that were added, removed or
+/// changed, in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SectionDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl SectionDiff {
+    /// Returns true if nothing was added, removed or changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The kinds of change that can break existing installations of a bundle, or the tools and
+/// people that use them
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreakingChange {
+    /// A parameter was removed, so values supplied for it are rejected
+    RemovedParameter(String),
+    /// A parameter without a default became required, or was added as required
+    RequiredParameter(String),
+    /// A credential became required, or was added as required
+    RequiredCredential(String),
+    /// An output was removed
+    RemovedOutput(String),
+    /// A custom action was removed
+    RemovedAction(String),
+    /// A definition no longer accepts every type of value it used to
+    DefinitionTypeChanged {
+        definition: String,
+        from: Option<SchemaType>,
+        to: Option<SchemaType>,
+    },
+    /// A definition that parameters or outputs still use was removed
+    RemovedDefinition(String),
+}
+
+impl std::fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakingChange::RemovedParameter(p) => write!(f, "parameter {:?} was removed", p),
+            BreakingChange::RequiredParameter(p) => write!(f, "parameter {:?} is now required", p),
+            BreakingChange::RequiredCredential(c) => {
+                write!(f, "credential {:?} is now required", c)
+            }
+            BreakingChange::RemovedOutput(o) => write!(f, "output {:?} was removed", o),
+            BreakingChange::RemovedAction(a) => write!(f, "action {:?} was removed", a),
+            BreakingChange::DefinitionTypeChanged {
+                definition,
+                from,
+                to,
+            } => {
+                let describe = |t: &Option<SchemaType>| match t {
+                    Some(t) => t.to_string(),
+                    None => "any type".to_string(),
+                };
+                write!(
+                    f,
+                    "definition {:?} changed type from {} to {}",
+                    definition,
+                    describe(from),
+                    describe(to)
+                )
+            }
+            BreakingChange::RemovedDefinition(d) => {
+                write!(f, "definition {:?} was removed but is still used", d)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn old() -> Bundle {
+        r#"{
+            "name": "aristotle",
+            "invocationImages": [
+                {"image": "technosophos/aristotle:1.0.0"}
+            ],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "actions": {
+                "status": {"stateless": true},
+                "backup": {"modifies": false}
+            },
+            "credentials": {
+                "kubeconfig": {"path": "/root/.kube/config", "required": true},
+                "token": {"env": "TOKEN"}
+            },
+            "parameters": {
+                "port": {"definition": "port", "destination": {"env": "PORT"}},
+                "debug": {"definition": "flag", "destination": {"env": "DEBUG"}},
+                "motd": {"definition": "text", "destination": {"path": "/etc/motd"}}
+            },
+            "outputs": {
+                "address": {"definition": "text"},
+                "log": {"definition": "text"}
+            },
+            "definitions": {
+                "port": {"type": "integer"},
+                "flag": {"type": "boolean"},
+                "text": {"type": "string"}
+            }
+        }"#
+        .parse()
+        .expect("parsed bundle")
+    }
+
+    fn new() -> Bundle {
+        r#"{
+            "name": "aristotle",
+            "invocationImages": [
+                {"image": "technosophos/aristotle:2.0.0"},
+                {"image": "technosophos/aristotle-windows:2.0.0"}
+            ],
+            "schemaVersion": "v1.0.0",
+            "version": "2.0.0",
+            "actions": {
+                "status": {"stateless": false}
+            },
+            "credentials": {
+                "kubeconfig": {"path": "/root/.kube/config", "required": true},
+                "token": {"env": "TOKEN", "required": true}
+            },
+            "parameters": {
+                "port": {"definition": "port", "destination": {"env": "PORT"}, "required": true},
+                "debug": {"definition": "flag", "destination": {"env": "DEBUG"}},
+                "replicas": {"definition": "count", "destination": {"env": "REPLICAS"}, "required": true},
+                "region": {"definition": "text", "destination": {"env": "REGION"}, "required": true}
+            },
+            "outputs": {
+                "address": {"definition": "text"}
+            },
+            "definitions": {
+                "port": {"type": ["integer", "string"]},
+                "flag": {"type": "string"},
+                "text": {"type": "string"},
+                "count": {"type": "integer", "default": 1}
+            }
+        }"#
+        .parse()
+        .expect("parsed bundle")
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_diff_sections() {
+        let diff = old().diff(&new());
+
+        assert_eq!(
+            diff.parameters,
+            SectionDiff {
+                added: names(&["region", "replicas"]),
+                removed: names(&["motd"]),
+                changed: names(&["port"]),
+            }
+        );
+        assert_eq!(diff.credentials.changed, names(&["token"]));
+        assert_eq!(diff.outputs.removed, names(&["log"]));
+        assert!(diff.images.is_empty());
+        assert_eq!(
+            diff.invocation_images,
+            SectionDiff {
+                added: names(&["1"]),
+                removed: vec![],
+                changed: names(&["0"]),
+            }
+        );
+        assert_eq!(diff.actions.removed, names(&["backup"]));
+        assert_eq!(diff.actions.changed, names(&["status"]));
+        assert_eq!(
+            diff.definitions,
+            SectionDiff {
+                added: names(&["count"]),
+                removed: vec![],
+                changed: names(&["flag", "port"]),
+            }
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_breaking_changes() {
+        let diff = old().diff(&new());
+        assert_eq!(
+            diff.breaking,
+            vec![
+                BreakingChange::RemovedParameter("motd".to_string()),
+                BreakingChange::RequiredParameter("port".to_string()),
+                BreakingChange::RequiredParameter("region".to_string()),
+                BreakingChange::RequiredCredential("token".to_string()),
+                BreakingChange::RemovedOutput("log".to_string()),
+                BreakingChange::RemovedAction("backup".to_string()),
+                BreakingChange::DefinitionTypeChanged {
+                    definition: "flag".to_string(),
+                    from: Some(SchemaType::Single(InstanceType::Boolean)),
+                    to: Some(SchemaType::Single(InstanceType::String)),
+                },
+            ]
+        );
+        assert_eq!(
+            diff.breaking[6].to_string(),
+            "definition \"flag\" changed type from boolean to string"
+        );
+    }
+
+    #[test]
+    fn test_diff_removed_definitions() {
+        let old = old();
+        let mut new = old.clone();
+        new.parameters.as_mut().unwrap().remove("debug");
+        let definitions = new.definitions.as_mut().unwrap();
+        definitions.remove("flag");
+        definitions.remove("text");
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.definitions.removed, names(&["flag", "text"]));
+        assert_eq!(
+            diff.breaking,
+            vec![
+                BreakingChange::RemovedParameter("debug".to_string()),
+                BreakingChange::RemovedDefinition("text".to_string()),
+            ]
+        );
+        assert_eq!(
+            diff.breaking[1].to_string(),
+            "definition \"text\" was removed but is still used"
+        );
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        let diff = old().diff(&old());
+        assert!(diff.is_empty());
+        assert!(!diff.is_breaking());
+    }
+}
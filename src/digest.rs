@@ -0,0 +1,365 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest as _, Sha256, Sha512};
+use std::io::{self, Read};
+use std::str::FromStr;
+
+use crate::canonical::CanonicalJSONError;
+use crate::cnab::{Bundle, Image, InvocationImage};
+
+/// Content digests of bundles.
+impl Bundle {
+    /// Compute the content digest of this bundle.
+    ///
+    /// The digest is the SHA-256 hash of the bundle's Canonical JSON form, which displays
+    /// as `sha256:<hex>`. This is the digest used to pin a bundle in a digested bundle
+    /// reference, such as `Claim::bundle_reference`.
+    ///
+    /// ```
+    /// use libcnab::Bundle;
+    ///
+    /// let bundle = Bundle::from_file("testdata/bundle.json").unwrap();
+    /// let digest = bundle.digest().unwrap();
+    /// assert_eq!(digest.algorithm(), "sha256");
+    /// assert!(digest.to_string().starts_with("sha256:"));
+    /// ```
+    pub fn digest(&self) -> Result<Digest, CanonicalJSONError> {
+        let json = self.to_canonical_json()?;
+        Ok(Digest::sha256(json.as_bytes()))
+    }
+
+    /// Check this bundle against an expected digest of the form `algorithm:hex`.
+    ///
+    /// Both `sha256` and `sha512` digests are supported. Returns an error if the expected
+    /// digest is malformed, uses another algorithm, or does not match the bundle.
+    pub fn verify_digest(&self, expected: &str) -> Result<(), DigestError> {
+        let expected: Digest = expected.parse()?;
+        let json = self.to_canonical_json()?;
+        expected.verify(json.as_bytes())
+    }
+}
+
+/// Content digests of images.
+impl Image {
+    /// Parse the `contentDigest` of this image, if it has one.
+    ///
+    /// Deserializing an image keeps the digest as written, so a placeholder such as
+    /// `sha256:aaaa...` does not stop a bundle from being read. It is reported here instead.
+    pub fn content_digest(&self) -> Result<Option<Digest>, DigestError> {
+        parse_content_digest(&self.content_digest)
+    }
+}
+
+/// Content digests of invocation images.
+impl InvocationImage {
+    /// Parse the `contentDigest` of this invocation image, if it has one.
+    ///
+    /// ```
+    /// use libcnab::{DigestError, InvocationImage};
+    ///
+    /// let image: InvocationImage = serde_json::from_str(
+    ///     r#"{"image": "technosophos/aristotle:1.0.0", "contentDigest": "sha256:aaaaaaa..."}"#,
+    /// ).unwrap();
+    /// match image.content_digest() {
+    ///     Err(DigestError::Malformed(d)) => assert_eq!(d, "sha256:aaaaaaa..."),
+    ///     other => panic!("expected a malformed digest, got {:?}", other),
+    /// }
+    /// ```
+    pub fn content_digest(&self) -> Result<Option<Digest>, DigestError> {
+        parse_content_digest(&self.content_digest)
+    }
+}
+
+fn parse_content_digest(digest: &Option<String>) -> Result<Option<Digest>, DigestError> {
+    digest.as_deref().map(str::parse).transpose()
+}
+
+/// Digest is a content digest of the form `algorithm:hex`, as used by OCI registries.
+///
+/// Any algorithm is accepted when parsing, as long as the digest is well formed. The
+/// encoded part of `sha256` and `sha512` digests must be lowercase hex of the right length.
+/// Only those two algorithms can be verified.
+///
+/// ```
+/// use libcnab::Digest;
+///
+/// let digest: Digest = "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(digest.algorithm(), "sha256");
+/// assert!(digest.verify("hello".as_bytes()).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Digest {
+    algorithm: String,
+    hex: String,
+}
+
+impl Digest {
+    /// Compute the SHA-256 digest of `data`.
+    pub fn sha256(data: &[u8]) -> Self {
+        Digest {
+            algorithm: "sha256".to_string(),
+            hex: to_hex(&Sha256::digest(data)),
+        }
+    }
+
+    /// Compute the SHA-512 digest of `data`.
+    pub fn sha512(data: &[u8]) -> Self {
+        Digest {
+            algorithm: "sha512".to_string(),
+            hex: to_hex(&Sha512::digest(data)),
+        }
+    }
+
+    /// The algorithm, such as `sha256`
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// The encoded hash
+    pub fn hex(&self) -> &str {
+        &self.hex
+    }
+
+    /// Check the contents of `reader` against this digest.
+    ///
+    /// The reader is consumed in chunks, so arbitrarily large blobs can be verified.
+    pub fn verify<R: Read>(&self, mut reader: R) -> Result<(), DigestError> {
+        let actual = match self.algorithm.as_str() {
+            "sha256" => {
+                let mut hasher = Sha256::new();
+                io::copy(&mut reader, &mut hasher)?;
+                to_hex(&hasher.finalize())
+            }
+            "sha512" => {
+                let mut hasher = Sha512::new();
+                io::copy(&mut reader, &mut hasher)?;
+                to_hex(&hasher.finalize())
+            }
+            _ => return Err(DigestError::UnsupportedAlgorithm(self.algorithm.clone())),
+        };
+        if actual != self.hex {
+            return Err(DigestError::Mismatch {
+                expected: self.to_string(),
+                actual: format!("{}:{}", self.algorithm, actual),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Digest {
+    type Err = DigestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || DigestError::Malformed(s.to_string());
+        let i = s.find(':').ok_or_else(malformed)?;
+        let (algorithm, hex) = (&s[..i], &s[i + 1..]);
+
+        let valid_algorithm = algorithm.split(|c| "+._-".contains(c)).all(|c| {
+            !c.is_empty()
+                && c.chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        });
+        let valid_hex = match algorithm {
+            "sha256" => is_lower_hex(hex, 64),
+            "sha512" => is_lower_hex(hex, 128),
+            _ => {
+                !hex.is_empty()
+                    && hex
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "=_-".contains(c))
+            }
+        };
+        if !valid_algorithm || !valid_hex {
+            return Err(malformed());
+        }
+
+        Ok(Digest {
+            algorithm: algorithm.to_string(),
+            hex: hex.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex)
+    }
+}
+
+impl Serialize for Digest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Digest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+fn is_lower_hex(hex: &str, len: usize) -> bool {
+    hex.len() == len
+        && hex
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Represents an error checking a digest
+#[derive(Debug)]
+pub enum DigestError {
+    CanonicalJSONError(CanonicalJSONError),
+    IoError(std::io::Error),
+    /// The digest is not of the form `algorithm:hex`, or its hex has the wrong length.
+    Malformed(String),
+    /// The digest algorithm is not one this library can compute.
+    UnsupportedAlgorithm(String),
+    /// The computed digest differs from the expected one.
+    Mismatch {
+        expected: String,
+        actual: String,
+    },
+}
+
+impl std::fmt::Display for DigestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DigestError::CanonicalJSONError(e) => write!(f, "{}", e),
+            DigestError::IoError(e) => write!(f, "{}", e),
+            DigestError::Malformed(d) => write!(f, "malformed digest {:?}", d),
+            DigestError::UnsupportedAlgorithm(a) => {
+                write!(f, "unsupported digest algorithm {:?}", a)
+            }
+            DigestError::Mismatch { expected, actual } => {
+                write!(f, "digest mismatch: expected {}, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DigestError {}
+
+impl From<CanonicalJSONError> for DigestError {
+    fn from(error: CanonicalJSONError) -> Self {
+        DigestError::CanonicalJSONError(error)
+    }
+}
+
+impl From<std::io::Error> for DigestError {
+    fn from(error: std::io::Error) -> Self {
+        DigestError::IoError(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SHA256: &str = "sha256:a284198152c18c3e0a2f20704950ed71acfc0dc6cc660b2ebcef10f3020746bc";
+    const SHA512: &str = "sha512:bc8cbf8ed1e66a212164d698980f681fde79da551ba55c0cc07d5461538fb7c05f2c014e30fed8e29685795682596e949e4866becc74a43c3c0cd2359d67308e";
+
+    fn bundle() -> Bundle {
+        r#"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0"
+        }"#
+        .parse()
+        .expect("parsed bundle")
+    }
+
+    #[test]
+    fn test_bundle_digest() {
+        assert_eq!(bundle().digest().expect("digest").to_string(), SHA256);
+    }
+
+    #[test]
+    fn test_bundle_verify_digest() {
+        let bun = bundle();
+        bun.verify_digest(SHA256).expect("sha256 matches");
+        bun.verify_digest(SHA512).expect("sha512 matches");
+
+        let wrong = format!("sha256:{}", "0".repeat(64));
+        match bun.verify_digest(&wrong) {
+            Err(DigestError::Mismatch { actual, .. }) => assert_eq!(actual, SHA256),
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bundle_verify_digest_errors() {
+        let bun = bundle();
+        match bun.verify_digest("sha256") {
+            Err(DigestError::Malformed(_)) => (),
+            other => panic!("expected a malformed digest, got {:?}", other),
+        }
+        match bun.verify_digest("md5:d41d8cd98f00b204e9800998ecf8427e") {
+            Err(DigestError::UnsupportedAlgorithm(a)) => assert_eq!(a, "md5"),
+            other => panic!("expected an unsupported algorithm, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_digest() {
+        let digest: Digest = SHA256.parse().expect("parsed digest");
+        assert_eq!(digest.algorithm(), "sha256");
+        assert_eq!(digest.hex().len(), 64);
+        assert_eq!(digest.to_string(), SHA256);
+
+        let digest: Digest = "multihash+base58:QmRZxt2b1FVZPNqd8hsiykDL3TdBDeTSPX9Kv46HmX4Gx8"
+            .parse()
+            .expect("parsed digest");
+        assert_eq!(digest.algorithm(), "multihash+base58");
+
+        for malformed in &[
+            "",
+            "sha256",
+            "sha256:",
+            ":abc",
+            "SHA256:abc",
+            "sha256:abc",
+            SHA256.to_uppercase().replace("SHA", "sha").as_str(),
+            format!("sha512:{}", "a".repeat(64)).as_str(),
+            "sha256:eeeeeeeee...",
+        ] {
+            match malformed.parse::<Digest>() {
+                Err(DigestError::Malformed(d)) => assert_eq!(d, *malformed),
+                other => panic!("expected {:?} to be malformed, got {:?}", malformed, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_digest_serde_round_trip() {
+        let digest: Digest = SHA512.parse().expect("parsed digest");
+        let json = serde_json::to_string(&digest).expect("serialized digest");
+        assert_eq!(json, format!("\"{}\"", SHA512));
+        let parsed: Digest = serde_json::from_str(&json).expect("deserialized digest");
+        assert_eq!(parsed, digest);
+    }
+
+    #[test]
+    fn test_digest_verify() {
+        let data = "hello".as_bytes();
+        let digest = Digest::sha256(data);
+        assert_eq!(
+            digest.hex(),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        digest.verify(data).expect("sha256 matches");
+        Digest::sha512(data).verify(data).expect("sha512 matches");
+
+        match digest.verify("goodbye".as_bytes()) {
+            Err(DigestError::Mismatch { expected, .. }) => assert_eq!(expected, digest.to_string()),
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::cnab::Bundle;
+
+/// Extension is a custom extension with a typed payload.
+///
+/// An extension is stored in `Bundle::custom` under its key. Implement this trait for the
+/// payload type, and use `Bundle::extension` and `Bundle::set_extension` to read and write
+/// it.
+///
+/// ```
+/// use libcnab::Extension;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Dependencies {
+///     requires: Vec<String>,
+/// }
+///
+/// impl Extension for Dependencies {
+///     const KEY: &'static str = "io.cnab.dependencies";
+/// }
+/// ```
+pub trait Extension: Serialize + DeserializeOwned {
+    /// The key the extension is stored under, such as `io.cnab.dependencies`
+    const KEY: &'static str;
+}
+
+/// Typed access to custom extensions.
+impl Bundle {
+    /// Read the extension `E` from `custom`.
+    ///
+    /// Returns `Ok(None)` if the bundle does not contain the extension, and an error if its
+    /// payload does not deserialize as an `E`.
+    pub fn extension<E: Extension>(&self) -> Result<Option<E>, ExtensionError> {
+        match self.custom.as_ref().and_then(|custom| custom.get(E::KEY)) {
+            Some(value) => E::deserialize(value)
+                .map(Some)
+                .map_err(|error| ExtensionError::new(E::KEY, error)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the extension `E` in `custom`, replacing any previous payload.
+    pub fn set_extension<E: Extension>(&mut self, extension: &E) -> Result<(), ExtensionError> {
+        let value =
+            serde_json::to_value(extension).map_err(|error| ExtensionError::new(E::KEY, error))?;
+        self.custom
+            .get_or_insert_with(BTreeMap::new)
+            .insert(E::KEY.to_string(), value);
+        Ok(())
+    }
+
+    /// Remove the extension `E` from `custom`, returning its raw payload.
+    pub fn remove_extension<E: Extension>(&mut self) -> Option<Value> {
+        let custom = self.custom.as_mut()?;
+        let value = custom.remove(E::KEY);
+        if custom.is_empty() {
+            self.custom = None;
+        }
+        value
+    }
+
+    /// Check that a runtime supporting the extensions with the given keys can run this
+    /// bundle.
+    ///
+    /// A runtime must refuse to run a bundle if it does not support every extension listed
+    /// in `requiredExtensions`. The error lists each one that is not supported.
+    ///
+    /// ```
+    /// use libcnab::Bundle;
+    ///
+    /// let bundle: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "requiredExtensions": ["io.cnab.dependencies", "io.cnab.parameter-sources"],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0"
+    /// }"#.parse().unwrap();
+    ///
+    /// let err = bundle
+    ///     .check_required_extensions(&["io.cnab.dependencies"])
+    ///     .unwrap_err();
+    /// assert_eq!(err.unsupported, vec!["io.cnab.parameter-sources"]);
+    /// ```
+    pub fn check_required_extensions<S: AsRef<str>>(
+        &self,
+        supported: &[S],
+    ) -> Result<(), RequiredExtensionsError> {
+        let unsupported: Vec<String> = self
+            .required_extensions
+            .iter()
+            .flatten()
+            .filter(|key| !supported.iter().any(|s| s.as_ref() == key.as_str()))
+            .cloned()
+            .collect();
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(RequiredExtensionsError { unsupported })
+        }
+    }
+}
+
+/// ExtensionRegistry records the extensions that a runtime understands.
+///
+/// ```
+/// use libcnab::{Bundle, Extension, ExtensionRegistry};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Dependencies {
+///     requires: Vec<String>,
+/// }
+///
+/// impl Extension for Dependencies {
+///     const KEY: &'static str = "io.cnab.dependencies";
+/// }
+///
+/// let mut registry = ExtensionRegistry::new();
+/// registry.register::<Dependencies>();
+///
+/// let bundle: Bundle = r#"{
+///     "name": "aristotle",
+///     "invocationImages": [],
+///     "schemaVersion": "v1.0.0",
+///     "version": "1.0.0",
+///     "custom": {
+///         "io.cnab.dependencies": {"requires": ["mysql"]},
+///         "com.example.color": "blue"
+///     }
+/// }"#.parse().unwrap();
+///
+/// assert!(registry.validate(&bundle).is_ok());
+/// assert_eq!(registry.unrecognized(&bundle), vec!["com.example.color"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionRegistry {
+    extensions: BTreeMap<&'static str, Check>,
+}
+
+/// Checks that a payload deserializes as the extension it was registered for.
+type Check = fn(&Value) -> Result<(), serde_json::Error>;
+
+impl ExtensionRegistry {
+    /// Create a registry with no extensions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare that the extension `E` is understood.
+    pub fn register<E: Extension>(&mut self) -> &mut Self {
+        self.extensions.insert(E::KEY, check::<E>);
+        self
+    }
+
+    /// Returns true if the extension with the given key has been registered.
+    pub fn supports(&self, key: &str) -> bool {
+        self.extensions.contains_key(key)
+    }
+
+    /// The keys of the registered extensions, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> + '_ {
+        self.extensions.keys().copied()
+    }
+
+    /// Check the payload of every registered extension that the bundle contains.
+    ///
+    /// Every payload that does not deserialize as its extension type is reported.
+    pub fn validate(&self, bundle: &Bundle) -> Result<(), Vec<ExtensionError>> {
+        let errors: Vec<ExtensionError> = bundle
+            .custom
+            .iter()
+            .flatten()
+            .filter_map(|(key, value)| {
+                let check = self.extensions.get(key.as_str())?;
+                check(value)
+                    .err()
+                    .map(|error| ExtensionError::new(key, error))
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check that every extension the bundle requires has been registered.
+    ///
+    /// See `Bundle::check_required_extensions`.
+    pub fn check_required(&self, bundle: &Bundle) -> Result<(), RequiredExtensionsError> {
+        let supported: Vec<&str> = self.keys().collect();
+        bundle.check_required_extensions(&supported)
+    }
+
+    /// The keys in the bundle's `custom` section that no registered extension uses.
+    pub fn unrecognized<'a>(&self, bundle: &'a Bundle) -> Vec<&'a str> {
+        bundle
+            .custom
+            .iter()
+            .flatten()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| !self.supports(key))
+            .collect()
+    }
+}
+
+fn check<E: Extension>(value: &Value) -> Result<(), serde_json::Error> {
+    E::deserialize(value).map(|_| ())
+}
+
+/// ExtensionError describes an extension payload that has the wrong structure.
+#[derive(Debug)]
+pub struct ExtensionError {
+    /// The key of the extension
+    pub key: String,
+    /// Why the payload could not be converted
+    pub error: serde_json::Error,
+}
+
+impl ExtensionError {
+    fn new(key: &str, error: serde_json::Error) -> Self {
+        ExtensionError {
+            key: key.to_string(),
+            error,
+        }
+    }
+}
+
+impl std::fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid extension {:?}: {}", self.key, self.error)
+    }
+}
+
+impl std::error::Error for ExtensionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// RequiredExtensionsError lists the required extensions of a bundle that a runtime does
+/// not support.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequiredExtensionsError {
+    /// The keys of the unsupported extensions, in the order the bundle lists them
+    pub unsupported: Vec<String>,
+}
+
+impl std::fmt::Display for RequiredExtensionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "bundle requires unsupported extensions: {}",
+            self.unsupported.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for RequiredExtensionsError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Dependencies {
+        requires: Vec<String>,
+    }
+
+    impl Extension for Dependencies {
+        const KEY: &'static str = "io.cnab.dependencies";
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Parameters {
+        sources: BTreeMap<String, String>,
+    }
+
+    impl Extension for Parameters {
+        const KEY: &'static str = "io.cnab.parameter-sources";
+    }
+
+    fn bundle() -> Bundle {
+        r#"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "requiredExtensions": ["io.cnab.dependencies", "com.example.color"],
+            "custom": {
+                "io.cnab.dependencies": {"requires": ["mysql"]},
+                "io.cnab.parameter-sources": {"sources": ["port"]},
+                "com.example.color": "blue"
+            }
+        }"#
+        .parse()
+        .expect("parsed bundle")
+    }
+
+    #[test]
+    fn test_extension_accessors() {
+        let mut bun = bundle();
+        let dependencies = bun.extension::<Dependencies>().expect("valid extension");
+        assert_eq!(
+            dependencies,
+            Some(Dependencies {
+                requires: vec!["mysql".to_string()]
+            })
+        );
+
+        let err = bun.extension::<Parameters>().unwrap_err();
+        assert_eq!(err.key, "io.cnab.parameter-sources");
+
+        bun.set_extension(&Dependencies {
+            requires: vec!["redis".to_string()],
+        })
+        .expect("set extension");
+        assert_eq!(
+            bun.custom.as_ref().unwrap()["io.cnab.dependencies"],
+            serde_json::json!({"requires": ["redis"]})
+        );
+
+        assert!(bun.remove_extension::<Dependencies>().is_some());
+        assert!(bun.extension::<Dependencies>().expect("absent").is_none());
+    }
+
+    #[test]
+    fn test_extension_registry() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register::<Dependencies>().register::<Parameters>();
+        assert!(registry.supports("io.cnab.dependencies"));
+        assert_eq!(
+            registry.keys().collect::<Vec<_>>(),
+            vec!["io.cnab.dependencies", "io.cnab.parameter-sources"]
+        );
+
+        let bun = bundle();
+        let errors = registry.validate(&bun).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key, "io.cnab.parameter-sources");
+        assert_eq!(registry.unrecognized(&bun), vec!["com.example.color"]);
+    }
+
+    #[test]
+    fn test_required_extensions() {
+        let bun = bundle();
+        assert!(bun
+            .check_required_extensions(&["com.example.color", "io.cnab.dependencies"])
+            .is_ok());
+
+        let mut registry = ExtensionRegistry::new();
+        registry.register::<Dependencies>();
+        let err = registry.check_required(&bun).unwrap_err();
+        assert_eq!(err.unsupported, vec!["com.example.color"]);
+        assert_eq!(
+            err.to_string(),
+            "bundle requires unsupported extensions: com.example.color"
+        );
+
+        let mut bun = bun;
+        bun.required_extensions = None;
+        assert!(registry.check_required(&bun).is_ok());
+    }
+}
@@ -1,10 +1,45 @@
 #![cfg_attr(test, deny(warnings))]
 #![warn(rust_2018_idioms)]
 
+mod action;
+pub use crate::action::*;
+mod builder;
+pub use crate::builder::*;
+mod canonical;
+pub use crate::canonical::*;
 mod cnab;
 pub use crate::cnab::*;
+mod schema;
+pub use crate::schema::*;
+mod spec;
+pub use crate::spec::*;
+mod migration;
+pub use crate::migration::*;
+mod outputs;
+pub use crate::outputs::*;
+mod parameters;
+pub use crate::parameters::*;
+mod reference;
+pub use crate::reference::*;
+mod strict;
+mod validation;
+pub use crate::validation::*;
+mod upgrade;
+pub use crate::upgrade::*;
+mod diff;
+pub use crate::diff::*;
+mod digest;
+pub use crate::digest::*;
+mod diagnostics;
+pub use crate::diagnostics::*;
+mod extension;
+pub use crate::extension::*;
+mod selector;
+pub use crate::selector::*;
 mod claim;
 pub use crate::claim::*;
+#[cfg(feature = "yaml")]
+mod yaml;
 
 // Re-export Ulid for convenience
 pub use ulid::Ulid;
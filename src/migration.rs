@@ -0,0 +1,528 @@
+use semver::Version;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::cnab::{self, Bundle, BundleParseError};
+use crate::digest::Digest;
+use crate::validation::pointer;
+
+/// The schema version that migrated bundles declare.
+const CORE_SCHEMA_VERSION: &str = "v1.0.0";
+
+/// Parameter fields that pre-1.0 bundles used to constrain values, and the JSON Schema
+/// keywords that replace them.
+const PARAMETER_KEYWORDS: [(&str, &str); 6] = [
+    ("defaultValue", "default"),
+    ("allowedValues", "enum"),
+    ("minValue", "minimum"),
+    ("maxValue", "maximum"),
+    ("minLength", "minLength"),
+    ("maxLength", "maxLength"),
+];
+
+/// Migration of pre-1.0 bundle documents.
+impl Bundle {
+    /// Deserialize a `Bundle` that may use fields from drafts of the CNAB specification
+    /// that predate CNAB Core 1.0, such as those written by Duffle.
+    ///
+    /// The document is rewritten with `migrate_document` before it is deserialized. A
+    /// document that is already a CNAB Core 1.0 bundle is returned unchanged, along with an
+    /// empty list of changes.
+    ///
+    /// ```
+    /// use libcnab::Bundle;
+    ///
+    /// let migration = Bundle::from_legacy_file("testdata/bundle.json").unwrap();
+    /// let port = &migration.bundle.parameters.as_ref().unwrap()["backend_port"];
+    /// assert_eq!(port.definition, Some("backend_port".to_string()));
+    /// for change in &migration.changes {
+    ///     println!("{}", change);
+    /// }
+    /// ```
+    pub fn from_legacy_json<R: Read>(reader: R) -> Result<Migration, BundleParseError> {
+        let mut document: Value = serde_json::from_reader(reader)?;
+        let changes = migrate_document(&mut document)?;
+        let bundle = cnab::deserialize(document)?;
+        Ok(Migration { bundle, changes })
+    }
+
+    /// A convenience function to open and migrate a legacy `bundle.json` file.
+    pub fn from_legacy_file<P: AsRef<Path>>(path: P) -> Result<Migration, BundleParseError> {
+        let file = File::open(path)?;
+        Self::from_legacy_json(file)
+    }
+}
+
+/// Migration is a bundle rewritten to CNAB Core 1.0, together with what was changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub bundle: Bundle,
+    pub changes: Vec<MigrationChange>,
+}
+
+impl Migration {
+    /// Returns true if the document needed any changes.
+    pub fn is_legacy(&self) -> bool {
+        !self.changes.is_empty()
+    }
+}
+
+/// Rewrite a pre-1.0 bundle document, in place, into a CNAB Core 1.0 document.
+///
+/// Older documents are recognized by their `schemaVersion` if it precedes `v1.0.0`, and
+/// otherwise by the legacy fields themselves. The following rewrites are made:
+///
+/// - `digest` on images and invocation images becomes `contentDigest`. Digests that are
+///   not of the form `algorithm:hex` are dropped, since they cannot identify any content.
+/// - An `imageType` of `docker` becomes `oci`.
+/// - Parameters using `type`, `defaultValue`, `allowedValues`, `minValue`, `maxValue`,
+///   `minLength` or `maxLength` get a generated definition holding those constraints,
+///   named after the parameter. `metadata.description` becomes the parameter's
+///   `description`.
+/// - A `schemaVersion` that precedes `v1.0.0` becomes `v1.0.0`.
+///
+/// Every change is returned, with a JSON pointer to the field in the original document.
+/// A `definitions` field that is not an object is reported as a
+/// `BundleParseError::SchemaError`, and the document is left unchanged.
+pub fn migrate_document(document: &mut Value) -> Result<Vec<MigrationChange>, BundleParseError> {
+    let mut changes = Vec::new();
+    let bundle = match document.as_object_mut() {
+        Some(bundle) => bundle,
+        None => return Ok(changes),
+    };
+    let mut definitions = match bundle.get("definitions") {
+        Some(Value::Object(definitions)) => definitions.clone(),
+        None | Some(Value::Null) => Map::new(),
+        Some(_) => {
+            return Err(BundleParseError::SchemaError {
+                pointer: pointer(&["definitions"]),
+                line: 0,
+                column: 0,
+                message: "expected definitions to be an object".to_string(),
+            });
+        }
+    };
+    let existing = definitions.len();
+
+    if let Some(Value::String(schema_version)) = bundle.get("schemaVersion") {
+        if is_pre_core(schema_version) {
+            changes.push(MigrationChange::new(
+                pointer(&["schemaVersion"]),
+                MigrationChangeKind::SchemaVersionUpdated(schema_version.clone()),
+            ));
+            bundle.insert(
+                "schemaVersion".to_string(),
+                Value::String(CORE_SCHEMA_VERSION.to_string()),
+            );
+        }
+    }
+
+    if let Some(Value::Array(images)) = bundle.get_mut("invocationImages") {
+        for (i, image) in images.iter_mut().enumerate() {
+            if let Some(image) = image.as_object_mut() {
+                let index = i.to_string();
+                migrate_image(&["invocationImages", index.as_str()], image, &mut changes);
+            }
+        }
+    }
+
+    if let Some(Value::Object(images)) = bundle.get_mut("images") {
+        for (name, image) in images.iter_mut() {
+            if let Some(image) = image.as_object_mut() {
+                migrate_image(&["images", name.as_str()], image, &mut changes);
+            }
+        }
+    }
+
+    if let Some(Value::Object(parameters)) = bundle.get_mut("parameters") {
+        for (name, parameter) in parameters.iter_mut() {
+            if let Some(parameter) = parameter.as_object_mut() {
+                migrate_parameter(name, parameter, &mut definitions, &mut changes);
+            }
+        }
+    }
+    // Leave the field as it was written unless a definition was generated.
+    if definitions.len() > existing {
+        bundle.insert("definitions".to_string(), Value::Object(definitions));
+    }
+
+    Ok(changes)
+}
+
+/// Returns true if `schema_version` names a draft that precedes CNAB Core 1.0.
+fn is_pre_core(schema_version: &str) -> bool {
+    let version = schema_version.strip_prefix('v').unwrap_or(schema_version);
+    match Version::parse(version) {
+        Ok(version) => version < Version::new(1, 0, 0),
+        Err(_) => false,
+    }
+}
+
+fn migrate_image(
+    path: &[&str],
+    image: &mut Map<String, Value>,
+    changes: &mut Vec<MigrationChange>,
+) {
+    let at = |field: &str| {
+        let mut path = path.to_vec();
+        path.push(field);
+        pointer(&path)
+    };
+
+    if let Some(digest) = image.remove("digest") {
+        let valid = digest.as_str().is_some_and(|d| d.parse::<Digest>().is_ok());
+        let kind = if image.contains_key("contentDigest") {
+            MigrationChangeKind::FieldDropped
+        } else if valid {
+            image.insert("contentDigest".to_string(), digest);
+            MigrationChangeKind::FieldRenamed("contentDigest".to_string())
+        } else {
+            MigrationChangeKind::InvalidDigestDropped(digest)
+        };
+        changes.push(MigrationChange::new(at("digest"), kind));
+    }
+
+    if image.get("imageType").and_then(Value::as_str) == Some("docker") {
+        image.insert("imageType".to_string(), Value::String("oci".to_string()));
+        changes.push(MigrationChange::new(
+            at("imageType"),
+            MigrationChangeKind::ImageTypeReplaced("docker".to_string()),
+        ));
+    }
+}
+
+fn migrate_parameter(
+    name: &str,
+    parameter: &mut Map<String, Value>,
+    definitions: &mut Map<String, Value>,
+    changes: &mut Vec<MigrationChange>,
+) {
+    let at = |field: &str| pointer(&["parameters", name, field]);
+
+    if let Some(Value::Object(mut metadata)) = parameter.remove("metadata") {
+        if let Some(description) = metadata.remove("description") {
+            if !parameter.contains_key("description") {
+                parameter.insert("description".to_string(), description);
+                changes.push(MigrationChange::new(
+                    pointer(&["parameters", name, "metadata", "description"]),
+                    MigrationChangeKind::FieldRenamed("description".to_string()),
+                ));
+            }
+        }
+        changes.push(MigrationChange::new(
+            at("metadata"),
+            MigrationChangeKind::FieldDropped,
+        ));
+    }
+
+    let mut schema = Map::new();
+    let mut legacy_fields = Vec::new();
+    if let Some(schema_type) = parameter.remove("type") {
+        legacy_fields.push("type");
+        let mapped = match schema_type.as_str() {
+            Some("int") | Some("integer") => Some("integer"),
+            Some("bool") | Some("boolean") => Some("boolean"),
+            Some("string") => Some("string"),
+            Some("number") => Some("number"),
+            Some("object") => Some("object"),
+            Some("array") => Some("array"),
+            _ => None,
+        };
+        match mapped {
+            Some(mapped) => {
+                schema.insert("type".to_string(), Value::String(mapped.to_string()));
+            }
+            None => changes.push(MigrationChange::new(
+                at("type"),
+                MigrationChangeKind::FieldDropped,
+            )),
+        }
+    }
+    for (legacy, keyword) in PARAMETER_KEYWORDS.iter() {
+        if let Some(value) = parameter.remove(*legacy) {
+            legacy_fields.push(legacy);
+            schema.insert(keyword.to_string(), value);
+        }
+    }
+    if schema.is_empty() {
+        return;
+    }
+
+    if parameter.contains_key("definition") {
+        // The parameter already names a definition, which supersedes the legacy fields.
+        for field in legacy_fields {
+            changes.push(MigrationChange::new(
+                at(field),
+                MigrationChangeKind::FieldDropped,
+            ));
+        }
+        return;
+    }
+
+    let mut definition = name.to_string();
+    let mut n = 1;
+    while definitions.contains_key(&definition) {
+        n += 1;
+        definition = format!("{}-{}", name, n);
+    }
+    definitions.insert(definition.clone(), Value::Object(schema));
+    parameter.insert("definition".to_string(), Value::String(definition.clone()));
+    changes.push(MigrationChange::new(
+        pointer(&["parameters", name]),
+        MigrationChangeKind::DefinitionGenerated(definition),
+    ));
+}
+
+/// MigrationChange describes one change made to a legacy bundle document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationChange {
+    /// A JSON pointer to the changed value in the original document
+    pub pointer: String,
+    /// What was changed
+    pub kind: MigrationChangeKind,
+}
+
+impl MigrationChange {
+    fn new(pointer: String, kind: MigrationChangeKind) -> Self {
+        MigrationChange { pointer, kind }
+    }
+}
+
+/// The kinds of change that `migrate_document` makes
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationChangeKind {
+    /// `schemaVersion` named a draft specification, and was replaced with `v1.0.0`
+    SchemaVersionUpdated(String),
+    /// The field was moved to the named field
+    FieldRenamed(String),
+    /// A `digest` could not be parsed, and was dropped
+    InvalidDigestDropped(Value),
+    /// The image type was replaced with `oci`
+    ImageTypeReplaced(String),
+    /// The parameter's legacy constraints were moved into the named definition
+    DefinitionGenerated(String),
+    /// The field has no equivalent in CNAB Core 1.0, and was dropped
+    FieldDropped,
+}
+
+impl std::fmt::Display for MigrationChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.pointer)?;
+        match &self.kind {
+            MigrationChangeKind::SchemaVersionUpdated(v) => {
+                write!(
+                    f,
+                    "replaced schema version {:?} with {:?}",
+                    v, CORE_SCHEMA_VERSION
+                )
+            }
+            MigrationChangeKind::FieldRenamed(to) => write!(f, "moved to {:?}", to),
+            MigrationChangeKind::InvalidDigestDropped(d) => {
+                write!(f, "dropped invalid digest {}", d)
+            }
+            MigrationChangeKind::ImageTypeReplaced(t) => {
+                write!(f, "replaced image type {:?} with \"oci\"", t)
+            }
+            MigrationChangeKind::DefinitionGenerated(d) => {
+                write!(f, "moved constraints into definition {:?}", d)
+            }
+            MigrationChangeKind::FieldDropped => write!(f, "dropped"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::{InstanceType, SchemaType};
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_testdata() {
+        let migration = Bundle::from_legacy_file("testdata/bundle.json").expect("migrated");
+        assert!(migration.is_legacy());
+
+        let found: Vec<(&str, &MigrationChangeKind)> = migration
+            .changes
+            .iter()
+            .map(|c| (c.pointer.as_str(), &c.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "/invocationImages/0/digest",
+                    &MigrationChangeKind::InvalidDigestDropped(json!("sha256:aaaaaaa..."))
+                ),
+                (
+                    "/invocationImages/0/imageType",
+                    &MigrationChangeKind::ImageTypeReplaced("docker".to_string())
+                ),
+                (
+                    "/images/my-microservice/digest",
+                    &MigrationChangeKind::InvalidDigestDropped(json!("sha256:aaaaaaaaaaaa..."))
+                ),
+                (
+                    "/parameters/backend_port/metadata/description",
+                    &MigrationChangeKind::FieldRenamed("description".to_string())
+                ),
+                (
+                    "/parameters/backend_port/metadata",
+                    &MigrationChangeKind::FieldDropped
+                ),
+                (
+                    "/parameters/backend_port",
+                    &MigrationChangeKind::DefinitionGenerated("backend_port".to_string())
+                ),
+            ]
+        );
+
+        let bun = migration.bundle;
+        assert_eq!(bun.invocation_images[0].image_type, Some("oci".to_string()));
+        let port = &bun.parameters.as_ref().expect("parameters")["backend_port"];
+        assert_eq!(port.definition, Some("backend_port".to_string()));
+        assert_eq!(
+            port.description,
+            Some("The port that the back-end will listen on".to_string())
+        );
+
+        let schema = bun.definitions.as_ref().expect("definitions")["backend_port"]
+            .as_schema()
+            .expect("schema");
+        assert_eq!(
+            schema.schema_type,
+            Some(SchemaType::Single(InstanceType::Integer))
+        );
+        assert_eq!(schema.default, Some(json!(80)));
+        assert_eq!(schema.minimum.as_ref().and_then(|n| n.as_u64()), Some(10));
+        assert_eq!(
+            schema.maximum.as_ref().and_then(|n| n.as_u64()),
+            Some(10240)
+        );
+        assert_eq!(bun.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_migrate_document() {
+        let mut document = json!({
+            "name": "aristotle",
+            "schemaVersion": "v1.0.0-WD",
+            "version": "1.0.0",
+            "invocationImages": [{
+                "image": "technosophos/aristotle:1.0.0",
+                "digest": "sha256:a284198152c18c3e0a2f20704950ed71acfc0dc6cc660b2ebcef10f3020746bc"
+            }],
+            "parameters": {
+                "color": {
+                    "type": "string",
+                    "allowedValues": ["red", "blue"],
+                    "destination": {"env": "COLOR"}
+                }
+            },
+            "definitions": {
+                "color": {"type": "boolean"}
+            }
+        });
+        let changes = migrate_document(&mut document).expect("migrated document");
+
+        let kinds: Vec<&MigrationChangeKind> = changes.iter().map(|c| &c.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &MigrationChangeKind::SchemaVersionUpdated("v1.0.0-WD".to_string()),
+                &MigrationChangeKind::FieldRenamed("contentDigest".to_string()),
+                &MigrationChangeKind::DefinitionGenerated("color-2".to_string()),
+            ]
+        );
+        assert_eq!(
+            document,
+            json!({
+                "name": "aristotle",
+                "schemaVersion": "v1.0.0",
+                "version": "1.0.0",
+                "invocationImages": [{
+                    "image": "technosophos/aristotle:1.0.0",
+                    "contentDigest": "sha256:a284198152c18c3e0a2f20704950ed71acfc0dc6cc660b2ebcef10f3020746bc"
+                }],
+                "parameters": {
+                    "color": {
+                        "definition": "color-2",
+                        "destination": {"env": "COLOR"}
+                    }
+                },
+                "definitions": {
+                    "color": {"type": "boolean"},
+                    "color-2": {"type": "string", "enum": ["red", "blue"]}
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_migrate_core_document() {
+        let mut document = json!({
+            "name": "aristotle",
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "invocationImages": [{"image": "technosophos/aristotle:1.0.0", "imageType": "oci"}]
+        });
+        let original = document.clone();
+        assert_eq!(migrate_document(&mut document).expect("migrated"), vec![]);
+        assert_eq!(document, original);
+    }
+
+    #[test]
+    fn test_migrate_document_definitions() {
+        let mut document = json!({
+            "name": "aristotle",
+            "schemaVersion": "v1.0.0-WD",
+            "version": "1.0.0",
+            "invocationImages": [],
+            "parameters": {
+                "port": {"type": "int", "defaultValue": 8080, "destination": {"env": "PORT"}}
+            }
+        });
+        let changes = migrate_document(&mut document).expect("migrated document");
+        assert!(changes.contains(&MigrationChange::new(
+            "/parameters/port".to_string(),
+            MigrationChangeKind::DefinitionGenerated("port".to_string()),
+        )));
+        assert_eq!(document["parameters"]["port"]["definition"], json!("port"));
+        assert_eq!(
+            document["definitions"],
+            json!({"port": {"type": "integer", "default": 8080}})
+        );
+
+        for definitions in &[json!({}), Value::Null] {
+            let mut document = json!({
+                "name": "aristotle",
+                "schemaVersion": "v1.0.0",
+                "version": "1.0.0",
+                "invocationImages": [],
+                "definitions": definitions
+            });
+            let original = document.clone();
+            let changes = migrate_document(&mut document).expect("migrated document");
+            assert!(changes.is_empty());
+            assert_eq!(document, original);
+        }
+
+        let mut document = json!({
+            "name": "aristotle",
+            "schemaVersion": "v1.0.0-WD",
+            "version": "1.0.0",
+            "invocationImages": [],
+            "definitions": []
+        });
+        let original = document.clone();
+        match migrate_document(&mut document) {
+            Err(BundleParseError::SchemaError { pointer, .. }) => {
+                assert_eq!(pointer, "/definitions")
+            }
+            other => panic!("expected a schema error, got {:?}", other),
+        }
+        assert_eq!(document, original);
+    }
+}
@@ -0,0 +1,263 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use crate::action::BundleAction;
+use crate::cnab::{Bundle, Output};
+use crate::schema::SchemaError;
+
+/// Output validation.
+impl Bundle {
+    /// Read and validate the outputs that an invocation image wrote while running `action`.
+    ///
+    /// Each output applicable to the action is read from the file in `dir` (inside the
+    /// invocation image this is `/cnab/app/outputs`) with the file name of its `path`, or
+    /// of the output's name if it has no path. The contents are interpreted according to
+    /// the output's definition, and validated against it. On success, the typed value of
+    /// every applicable output is returned. Otherwise every failure is returned, so a
+    /// runtime can tell exactly why a run did not succeed.
+    ///
+    /// ```no_run
+    /// use libcnab::{Bundle, BundleAction};
+    ///
+    /// let bundle = Bundle::from_file("bundle.json").unwrap();
+    /// let outputs = bundle
+    ///     .validate_outputs(&BundleAction::Install, "/cnab/app/outputs")
+    ///     .unwrap();
+    /// for (name, value) in outputs {
+    ///     println!("{} = {}", name, value);
+    /// }
+    /// ```
+    pub fn validate_outputs<P: AsRef<Path>>(
+        &self,
+        action: &BundleAction,
+        dir: P,
+    ) -> Result<BTreeMap<String, Value>, Vec<OutputError>> {
+        let mut values = BTreeMap::new();
+        let mut errors = Vec::new();
+
+        if let Some(outputs) = &self.outputs {
+            for (name, output) in outputs {
+                if !output.applies_to(action) {
+                    continue;
+                }
+                let file = dir.as_ref().join(output_file_name(name, output));
+                match self.read_output(output, &file) {
+                    Ok(value) => {
+                        values.insert(name.clone(), value);
+                    }
+                    Err(kind) => errors.push(OutputError {
+                        output: name.clone(),
+                        kind,
+                    }),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(values)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn read_output(&self, output: &Output, path: &Path) -> Result<Value, OutputErrorKind> {
+        let raw = fs::read_to_string(path).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => OutputErrorKind::Missing,
+            _ => OutputErrorKind::Unreadable(e.to_string()),
+        })?;
+
+        let empty = BTreeMap::new();
+        let definitions = self.definitions.as_ref().unwrap_or(&empty);
+        let undefined = || OutputErrorKind::UndefinedDefinition(output.definition.clone());
+        let schema = definitions
+            .get(&output.definition)
+            .ok_or_else(undefined)?
+            .dereference(definitions)
+            .ok_or_else(undefined)?;
+
+        let value = schema.parse_value(&raw);
+        schema
+            .validate(&value, definitions)
+            .map_err(OutputErrorKind::Invalid)?;
+        Ok(value)
+    }
+}
+
+/// The name of the file that an output is written to.
+fn output_file_name<'a>(name: &'a str, output: &'a Output) -> &'a OsStr {
+    output
+        .path
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .unwrap_or_else(|| OsStr::new(name))
+}
+
+/// OutputError describes why an output was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputError {
+    /// The name of the output
+    pub output: String,
+    /// Why the output was rejected
+    pub kind: OutputErrorKind,
+}
+
+/// The ways in which an output can be rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputErrorKind {
+    /// The invocation image did not write the output
+    Missing,
+    /// The output file exists but could not be read
+    Unreadable(String),
+    /// The output's definition is not present in the bundle's `definitions`
+    UndefinedDefinition(String),
+    /// The output does not conform to its definition
+    Invalid(Vec<SchemaError>),
+}
+
+impl std::fmt::Display for OutputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "output {:?}: ", self.output)?;
+        match &self.kind {
+            OutputErrorKind::Missing => write!(f, "the output was not written"),
+            OutputErrorKind::Unreadable(e) => write!(f, "cannot read the output: {}", e),
+            OutputErrorKind::UndefinedDefinition(d) => {
+                write!(f, "definition {:?} is not defined", d)
+            }
+            OutputErrorKind::Invalid(errors) => {
+                let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", messages.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    /// A scratch directory that is removed when dropped.
+    struct OutputDir(PathBuf);
+
+    impl OutputDir {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "libcnab-outputs-{}-{}",
+                name,
+                std::process::id()
+            ));
+            fs::create_dir_all(&dir).expect("created output dir");
+            for (file, contents) in files {
+                fs::write(dir.join(file), contents).expect("wrote output file");
+            }
+            OutputDir(dir)
+        }
+    }
+
+    impl Drop for OutputDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn bundle() -> Bundle {
+        r##"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "outputs": {
+                "address": {
+                    "definition": "address",
+                    "path": "/cnab/app/outputs/ip"
+                },
+                "port": {
+                    "applyTo": ["install", "upgrade"],
+                    "definition": "port"
+                },
+                "report": {
+                    "applyTo": ["status"],
+                    "definition": "report"
+                }
+            },
+            "definitions": {
+                "address": {"type": "string"},
+                "port": {"$ref": "#/definitions/number"},
+                "number": {"type": "integer", "minimum": 1},
+                "report": {"type": "object", "required": ["healthy"]}
+            }
+        }"##
+        .parse()
+        .expect("parsed bundle")
+    }
+
+    #[test]
+    fn test_validate_outputs() {
+        let dir = OutputDir::new(
+            "valid",
+            &[
+                ("ip", "10.0.0.1"),
+                ("address", "?"),
+                ("port", "8080\n"),
+                ("extra", "?"),
+            ],
+        );
+        let outputs = bundle()
+            .validate_outputs(&BundleAction::Install, &dir.0)
+            .expect("valid outputs");
+
+        let mut expected = BTreeMap::new();
+        expected.insert("address".to_string(), json!("10.0.0.1"));
+        expected.insert("port".to_string(), json!(8080));
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_validate_outputs_errors() {
+        let dir = OutputDir::new(
+            "invalid",
+            &[("address", "10.0.0.1"), ("port", "0"), ("report", "{}")],
+        );
+        let errors = bundle()
+            .validate_outputs(&BundleAction::Upgrade, &dir.0)
+            .expect_err("invalid outputs");
+
+        let found: Vec<(&str, &OutputErrorKind)> = errors
+            .iter()
+            .map(|e| (e.output.as_str(), &e.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("address", &OutputErrorKind::Missing),
+                (
+                    "port",
+                    &OutputErrorKind::Invalid(vec![SchemaError {
+                        pointer: "".to_string(),
+                        message: "0 is less than the minimum of 1".to_string(),
+                    }])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_outputs_custom_action() {
+        let dir = OutputDir::new("custom", &[("ip", "here"), ("report", "{}")]);
+        let errors = bundle()
+            .validate_outputs(&"status".into(), &dir.0)
+            .expect_err("invalid report");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "output \"report\": missing required property \"healthy\""
+        );
+    }
+}
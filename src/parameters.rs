@@ -0,0 +1,427 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::action::BundleAction;
+use crate::cnab::{Bundle, Parameter};
+use crate::schema::{SchemaError, SchemaOrBool};
+
+/// Parameter value validation.
+impl Bundle {
+    /// Validate user-supplied parameter values against this bundle's parameters.
+    ///
+    /// Each value is checked against the schema its parameter's `definition` names in
+    /// `definitions`. Every required parameter must have a value, and every value must
+    /// belong to a declared parameter. All failures are reported, keyed by parameter.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, ParameterErrorKind};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let bundle: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0",
+    ///     "parameters": {
+    ///         "port": {"definition": "port", "destination": {"env": "PORT"}, "required": true}
+    ///     },
+    ///     "definitions": {
+    ///         "port": {"type": "integer"}
+    ///     }
+    /// }"#.parse().unwrap();
+    ///
+    /// let mut values = BTreeMap::new();
+    /// values.insert("port".to_string(), serde_json::json!(8080));
+    /// assert!(bundle.validate_parameters(&values).is_ok());
+    ///
+    /// let errors = bundle.validate_parameters(&BTreeMap::new()).unwrap_err();
+    /// assert_eq!(errors[0].parameter, "port");
+    /// assert_eq!(errors[0].kind, ParameterErrorKind::Missing);
+    /// ```
+    pub fn validate_parameters(
+        &self,
+        values: &BTreeMap<String, Value>,
+    ) -> Result<(), Vec<ParameterError>> {
+        let empty = BTreeMap::new();
+        let parameters = self.parameters.as_ref().unwrap_or(&empty);
+        let mut errors = Vec::new();
+
+        for name in values.keys() {
+            if !parameters.contains_key(name) {
+                errors.push(ParameterError::new(name, ParameterErrorKind::Undeclared));
+            }
+        }
+
+        for (name, parameter) in parameters {
+            match values.get(name) {
+                Some(value) => {
+                    if let Err(kind) = self.check_parameter_value(parameter, value) {
+                        errors.push(ParameterError::new(name, kind));
+                    }
+                }
+                None if parameter.required.unwrap_or(false) => {
+                    errors.push(ParameterError::new(name, ParameterErrorKind::Missing));
+                }
+                None => {}
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Resolve the final parameter values for running `action`.
+    ///
+    /// Parameters whose `apply_to` excludes the action are dropped, along with any value
+    /// supplied for them. Every other parameter takes the supplied value, falling back to
+    /// the `default` of its definition. The result holds one validated value for each
+    /// applicable parameter that has one. If any required parameter is left without a
+    /// value, or any value is invalid, all of the failures are returned instead.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, BundleAction};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let bundle: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0",
+    ///     "parameters": {
+    ///         "port": {"definition": "port", "destination": {"env": "PORT"}}
+    ///     },
+    ///     "definitions": {
+    ///         "port": {"type": "integer", "default": 8080}
+    ///     }
+    /// }"#.parse().unwrap();
+    ///
+    /// let resolved = bundle.resolve_parameters(&BundleAction::Install, BTreeMap::new()).unwrap();
+    /// assert_eq!(resolved["port"], serde_json::json!(8080));
+    /// ```
+    pub fn resolve_parameters(
+        &self,
+        action: &BundleAction,
+        mut values: BTreeMap<String, Value>,
+    ) -> Result<BTreeMap<String, Value>, Vec<ParameterError>> {
+        let empty = BTreeMap::new();
+        let parameters = self.parameters.as_ref().unwrap_or(&empty);
+        let mut errors = Vec::new();
+        let mut resolved = BTreeMap::new();
+
+        for name in values.keys() {
+            if !parameters.contains_key(name) {
+                errors.push(ParameterError::new(name, ParameterErrorKind::Undeclared));
+            }
+        }
+
+        for (name, parameter) in parameters {
+            if !parameter.applies_to(action) {
+                continue;
+            }
+            let value = values
+                .remove(name)
+                .or_else(|| self.parameter_default(parameter).cloned());
+            match value {
+                Some(value) => match self.check_parameter_value(parameter, &value) {
+                    Ok(()) => {
+                        resolved.insert(name.clone(), value);
+                    }
+                    Err(kind) => errors.push(ParameterError::new(name, kind)),
+                },
+                None if parameter.required.unwrap_or(false) => {
+                    errors.push(ParameterError::new(name, ParameterErrorKind::Missing));
+                }
+                None => {}
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(resolved)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the schema that a parameter's `definition` names, or `None` if the
+    /// parameter has no definition.
+    pub(crate) fn parameter_definition(
+        &self,
+        parameter: &Parameter,
+    ) -> Result<Option<&SchemaOrBool>, ParameterErrorKind> {
+        let definition = match &parameter.definition {
+            Some(definition) => definition,
+            None => return Ok(None),
+        };
+        self.definitions
+            .as_ref()
+            .and_then(|definitions| definitions.get(definition))
+            .map(Some)
+            .ok_or_else(|| ParameterErrorKind::UndefinedDefinition(definition.clone()))
+    }
+
+    /// Returns the default value from a parameter's definition, following `$ref`s, if it
+    /// has one.
+    pub(crate) fn parameter_default(&self, parameter: &Parameter) -> Option<&Value> {
+        let definitions = self.definitions.as_ref()?;
+        self.parameter_definition(parameter)
+            .ok()??
+            .dereference(definitions)?
+            .as_schema()?
+            .default
+            .as_ref()
+    }
+
+    /// Check one value against the definition of its parameter.
+    ///
+    /// A parameter without a definition accepts any value.
+    pub(crate) fn check_parameter_value(
+        &self,
+        parameter: &Parameter,
+        value: &Value,
+    ) -> Result<(), ParameterErrorKind> {
+        let schema = match self.parameter_definition(parameter)? {
+            Some(schema) => schema,
+            None => return Ok(()),
+        };
+        let empty = BTreeMap::new();
+        let definitions = self.definitions.as_ref().unwrap_or(&empty);
+        schema
+            .validate(value, definitions)
+            .map_err(ParameterErrorKind::Invalid)
+    }
+}
+
+/// ParameterError describes why a parameter value was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterError {
+    /// The name of the parameter
+    pub parameter: String,
+    /// Why the parameter was rejected
+    pub kind: ParameterErrorKind,
+}
+
+impl ParameterError {
+    fn new(parameter: &str, kind: ParameterErrorKind) -> Self {
+        ParameterError {
+            parameter: parameter.to_string(),
+            kind,
+        }
+    }
+}
+
+/// The ways in which a parameter value can be rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterErrorKind {
+    /// The parameter is required, but no value was supplied
+    Missing,
+    /// A value was supplied for a parameter the bundle does not declare
+    Undeclared,
+    /// The parameter's definition is not present in the bundle's `definitions`
+    UndefinedDefinition(String),
+    /// The value does not conform to the parameter's definition
+    Invalid(Vec<SchemaError>),
+}
+
+impl std::fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "parameter {:?}: ", self.parameter)?;
+        match &self.kind {
+            ParameterErrorKind::Missing => write!(f, "a value is required"),
+            ParameterErrorKind::Undeclared => write!(f, "the bundle has no such parameter"),
+            ParameterErrorKind::UndefinedDefinition(d) => {
+                write!(f, "definition {:?} is not defined", d)
+            }
+            ParameterErrorKind::Invalid(errors) => {
+                let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", messages.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParameterError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn bundle() -> Bundle {
+        r#"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "parameters": {
+                "port": {
+                    "definition": "port",
+                    "destination": {"env": "PORT"},
+                    "required": true
+                },
+                "motd": {
+                    "definition": "message",
+                    "destination": {"path": "/etc/motd"}
+                },
+                "anything": {
+                    "destination": {"env": "ANYTHING"}
+                },
+                "broken": {
+                    "definition": "missing",
+                    "destination": {"env": "BROKEN"}
+                }
+            },
+            "definitions": {
+                "port": {"type": "integer", "minimum": 1, "maximum": 65535},
+                "message": {"type": "string", "maxLength": 5}
+            }
+        }"#
+        .parse()
+        .expect("parsed bundle")
+    }
+
+    fn values(value: Value) -> BTreeMap<String, Value> {
+        serde_json::from_value(value).expect("parameter values")
+    }
+
+    #[test]
+    fn test_validate_parameters() {
+        let bun = bundle();
+        assert_eq!(
+            bun.validate_parameters(&values(json!({"port": 80, "anything": [1, "two"]}))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_parameters_errors() {
+        let bun = bundle();
+        let errors = bun
+            .validate_parameters(&values(json!({
+                "motd": "hello world",
+                "broken": true,
+                "extra": 1
+            })))
+            .expect_err("invalid parameters");
+
+        let found: Vec<(&str, &ParameterErrorKind)> = errors
+            .iter()
+            .map(|e| (e.parameter.as_str(), &e.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("extra", &ParameterErrorKind::Undeclared),
+                (
+                    "broken",
+                    &ParameterErrorKind::UndefinedDefinition("missing".to_string())
+                ),
+                (
+                    "motd",
+                    &ParameterErrorKind::Invalid(vec![SchemaError {
+                        pointer: "".to_string(),
+                        message: "string is longer than the maximum length of 5".to_string(),
+                    }])
+                ),
+                ("port", &ParameterErrorKind::Missing),
+            ]
+        );
+        assert_eq!(
+            errors[2].to_string(),
+            "parameter \"motd\": string is longer than the maximum length of 5"
+        );
+    }
+
+    #[test]
+    fn test_resolve_parameters() {
+        let bun: Bundle = r##"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "parameters": {
+                "port": {
+                    "definition": "port",
+                    "destination": {"env": "PORT"},
+                    "required": true
+                },
+                "replicas": {
+                    "applyTo": ["install", "upgrade"],
+                    "definition": "count",
+                    "destination": {"env": "REPLICAS"}
+                },
+                "force": {
+                    "applyTo": ["uninstall"],
+                    "definition": "flag",
+                    "destination": {"env": "FORCE"},
+                    "required": true
+                },
+                "note": {
+                    "destination": {"env": "NOTE"}
+                },
+                "host": {
+                    "definition": "host",
+                    "destination": {"env": "HOST"},
+                    "required": true
+                }
+            },
+            "definitions": {
+                "port": {"type": "integer", "default": 8080},
+                "count": {"type": "integer", "minimum": 1},
+                "flag": {"type": "boolean"},
+                "host": {"$ref": "#/definitions/address"},
+                "address": {"type": "string", "default": "localhost"}
+            }
+        }"##
+        .parse()
+        .expect("parsed bundle");
+
+        // Defaults fill gaps, including through a `$ref`, and parameters for other actions
+        // are dropped.
+        let resolved = bun
+            .resolve_parameters(
+                &BundleAction::Install,
+                values(json!({"replicas": 3, "force": true})),
+            )
+            .expect("resolved parameters");
+        assert_eq!(
+            resolved,
+            values(json!({"host": "localhost", "port": 8080, "replicas": 3}))
+        );
+
+        // Supplied values win over defaults.
+        let resolved = bun
+            .resolve_parameters(
+                &BundleAction::Upgrade,
+                values(json!({"port": 9090, "note": "hi"})),
+            )
+            .expect("resolved parameters");
+        assert_eq!(
+            resolved,
+            values(json!({"host": "localhost", "port": 9090, "note": "hi"}))
+        );
+
+        // Required parameters for the action must be present.
+        let errors = bun
+            .resolve_parameters(&BundleAction::Uninstall, values(json!({"replicas": 0})))
+            .expect_err("force is missing");
+        assert_eq!(
+            errors,
+            vec![ParameterError::new("force", ParameterErrorKind::Missing)]
+        );
+    }
+
+    #[test]
+    fn test_resolve_parameters_invalid() {
+        let errors = bundle()
+            .resolve_parameters(
+                &BundleAction::Install,
+                values(json!({"port": "eighty", "nope": 1})),
+            )
+            .expect_err("invalid parameters");
+        let names: Vec<&str> = errors.iter().map(|e| e.parameter.as_str()).collect();
+        assert_eq!(names, vec!["nope", "port"]);
+    }
+}
@@ -0,0 +1,435 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+
+use crate::claim::Claim;
+use crate::cnab::{Image, InvocationImage};
+use crate::digest::Digest;
+
+/// The registry that references without an explicit registry resolve against.
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// The maximum length of a registry and repository name combined.
+const MAX_NAME_LENGTH: usize = 255;
+
+/// The maximum length of a tag.
+const MAX_TAG_LENGTH: usize = 128;
+
+/// ImageReference is a parsed and normalized OCI (or Docker) image reference.
+///
+/// Parsing follows the Docker distribution rules:
+///
+/// - If the first path component contains a `.` or a `:`, is `localhost`, or contains
+///   uppercase letters, it is the registry. Otherwise the registry is `docker.io`.
+/// - `index.docker.io` is an alias for `docker.io`.
+/// - A single-component repository on `docker.io` belongs to `library/`.
+///
+/// The normalized form is returned by `to_string()`, and is also used when serializing.
+///
+/// ```
+/// use libcnab::ImageReference;
+///
+/// let reference: ImageReference = "technosophos/helloworld:0.1.0".parse().unwrap();
+/// assert_eq!(reference.registry(), "docker.io");
+/// assert_eq!(reference.repository(), "technosophos/helloworld");
+/// assert_eq!(reference.tag(), Some("0.1.0"));
+/// assert_eq!(reference.to_string(), "docker.io/technosophos/helloworld:0.1.0");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageReference {
+    registry: String,
+    port: Option<u16>,
+    repository: String,
+    tag: Option<String>,
+    digest: Option<Digest>,
+}
+
+impl ImageReference {
+    /// The registry host, without the port
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+
+    /// The registry port, if one was given
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// The repository path within the registry, such as `library/nginx`
+    pub fn repository(&self) -> &str {
+        &self.repository
+    }
+
+    /// The tag, if one was given
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// The digest, if one was given
+    pub fn digest(&self) -> Option<&Digest> {
+        self.digest.as_ref()
+    }
+
+    /// The registry and repository, without the tag or digest.
+    pub fn name(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{}/{}", self.registry, port, self.repository),
+            None => format!("{}/{}", self.registry, self.repository),
+        }
+    }
+}
+
+impl FromStr for ImageReference {
+    type Err = ReferenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ReferenceError::Empty);
+        }
+
+        let (name, digest) = match s.find('@') {
+            Some(i) => (&s[..i], Some(parse_digest(&s[i + 1..])?)),
+            None => (s, None),
+        };
+
+        let (name, tag) = match name.rfind(':') {
+            Some(i) if !name[i + 1..].contains('/') => {
+                (&name[..i], Some(parse_tag(&name[i + 1..])?))
+            }
+            _ => (name, None),
+        };
+
+        let (domain, path) = match name.find('/') {
+            Some(i) if is_domain(&name[..i]) => (&name[..i], &name[i + 1..]),
+            _ => (DEFAULT_REGISTRY, name),
+        };
+
+        let (registry, port) = match domain.rfind(':') {
+            Some(i) => {
+                let port = &domain[i + 1..];
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|_| ReferenceError::InvalidPort(port.to_string()))?;
+                (&domain[..i], Some(port))
+            }
+            None => (domain, None),
+        };
+        if !is_hostname(registry) {
+            return Err(ReferenceError::InvalidRegistry(domain.to_string()));
+        }
+        let registry = if registry == "index.docker.io" {
+            DEFAULT_REGISTRY
+        } else {
+            registry
+        };
+
+        if !path.split('/').all(is_path_component) {
+            return Err(ReferenceError::InvalidRepository(path.to_string()));
+        }
+        let repository = if registry == DEFAULT_REGISTRY && port.is_none() && !path.contains('/') {
+            format!("library/{}", path)
+        } else {
+            path.to_string()
+        };
+
+        let reference = ImageReference {
+            registry: registry.to_string(),
+            port,
+            repository,
+            tag,
+            digest,
+        };
+        let length = reference.name().len();
+        if length > MAX_NAME_LENGTH {
+            return Err(ReferenceError::NameTooLong(length));
+        }
+        Ok(reference)
+    }
+}
+
+impl std::fmt::Display for ImageReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for ImageReference {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageReference {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Returns true if the first component of a reference names a registry.
+fn is_domain(component: &str) -> bool {
+    component == "localhost"
+        || component.contains('.')
+        || component.contains(':')
+        || component.chars().any(|c| c.is_ascii_uppercase())
+}
+
+/// Returns true if `host` is a sequence of dot-separated DNS labels.
+fn is_hostname(host: &str) -> bool {
+    host.split('.').all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+/// Returns true if `component` is a valid repository path component: lowercase
+/// alphanumerics, separated by a `.`, a `_`, a `__`, or any number of `-`.
+fn is_path_component(component: &str) -> bool {
+    let bytes = component.as_bytes();
+    let alphanumeric = |b: &u8| b.is_ascii_lowercase() || b.is_ascii_digit();
+    let (first, last) = match (bytes.first(), bytes.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return false,
+    };
+    if !alphanumeric(first) || !alphanumeric(last) {
+        return false;
+    }
+
+    let mut separator = String::new();
+    for &b in bytes {
+        if alphanumeric(&b) {
+            let valid = matches!(separator.as_str(), "" | "." | "_" | "__")
+                || separator.bytes().all(|s| s == b'-');
+            if !valid {
+                return false;
+            }
+            separator.clear();
+        } else {
+            separator.push(b as char);
+        }
+    }
+    true
+}
+
+fn parse_tag(tag: &str) -> Result<String, ReferenceError> {
+    let valid = tag.len() <= MAX_TAG_LENGTH
+        && tag.chars().enumerate().all(|(i, c)| {
+            c.is_ascii_alphanumeric() || c == '_' || (i > 0 && (c == '.' || c == '-'))
+        })
+        && !tag.is_empty();
+    if valid {
+        Ok(tag.to_string())
+    } else {
+        Err(ReferenceError::InvalidTag(tag.to_string()))
+    }
+}
+
+fn parse_digest(digest: &str) -> Result<Digest, ReferenceError> {
+    digest
+        .parse()
+        .map_err(|_| ReferenceError::InvalidDigest(digest.to_string()))
+}
+
+/// Image reference parsing.
+impl Image {
+    /// Parse the image reference.
+    pub fn parse_reference(&self) -> Result<ImageReference, ReferenceError> {
+        self.image.parse()
+    }
+}
+
+/// Image reference parsing.
+impl InvocationImage {
+    /// Parse the image reference.
+    pub fn parse_reference(&self) -> Result<ImageReference, ReferenceError> {
+        self.image.parse()
+    }
+}
+
+/// Bundle reference parsing.
+impl Claim {
+    /// Parse the bundle reference, if the claim has one.
+    pub fn parse_bundle_reference(&self) -> Result<Option<ImageReference>, ReferenceError> {
+        self.bundle_reference
+            .as_ref()
+            .map(|reference| reference.parse())
+            .transpose()
+    }
+}
+
+/// ReferenceError describes why an image reference could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceError {
+    /// The reference is empty
+    Empty,
+    /// The registry is not a valid hostname
+    InvalidRegistry(String),
+    /// The registry port is not a number between 0 and 65535
+    InvalidPort(String),
+    /// The repository path contains an invalid component
+    InvalidRepository(String),
+    /// The tag contains invalid characters or is too long
+    InvalidTag(String),
+    /// The digest is not of the form `algorithm:hex`
+    InvalidDigest(String),
+    /// The registry and repository are longer than 255 characters
+    NameTooLong(usize),
+}
+
+impl std::fmt::Display for ReferenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReferenceError::Empty => write!(f, "image reference is empty"),
+            ReferenceError::InvalidRegistry(r) => write!(f, "invalid registry {:?}", r),
+            ReferenceError::InvalidPort(p) => write!(f, "invalid registry port {:?}", p),
+            ReferenceError::InvalidRepository(r) => write!(f, "invalid repository {:?}", r),
+            ReferenceError::InvalidTag(t) => write!(f, "invalid tag {:?}", t),
+            ReferenceError::InvalidDigest(d) => write!(f, "invalid digest {:?}", d),
+            ReferenceError::NameTooLong(n) => write!(
+                f,
+                "repository name is {} characters long, the maximum is {}",
+                n, MAX_NAME_LENGTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReferenceError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DIGEST: &str = "sha256:a284198152c18c3e0a2f20704950ed71acfc0dc6cc660b2ebcef10f3020746bc";
+
+    fn parse(s: &str) -> ImageReference {
+        s.parse().expect("parsed reference")
+    }
+
+    #[test]
+    fn test_parse_reference() {
+        let cases = vec![
+            ("nginx", "docker.io", None, "library/nginx", None),
+            (
+                "nginx:1.17",
+                "docker.io",
+                None,
+                "library/nginx",
+                Some("1.17"),
+            ),
+            (
+                "index.docker.io/technosophos/helloworld:0.1.0",
+                "docker.io",
+                None,
+                "technosophos/helloworld",
+                Some("0.1.0"),
+            ),
+            (
+                "localhost:5000/bundle",
+                "localhost",
+                Some(5000),
+                "bundle",
+                None,
+            ),
+            (
+                "hub.example.com/my/deep/bundle:v1",
+                "hub.example.com",
+                None,
+                "my/deep/bundle",
+                Some("v1"),
+            ),
+            ("Registry/a__b-c.d", "Registry", None, "a__b-c.d", None),
+        ];
+        for (input, registry, port, repository, tag) in cases {
+            let reference = parse(input);
+            assert_eq!(reference.registry(), registry, "{}", input);
+            assert_eq!(reference.port(), port, "{}", input);
+            assert_eq!(reference.repository(), repository, "{}", input);
+            assert_eq!(reference.tag(), tag, "{}", input);
+            assert_eq!(reference.digest(), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_reference_with_digest() {
+        let reference = parse(&format!("example.com:443/app:1.0@{}", DIGEST));
+        assert_eq!(reference.registry(), "example.com");
+        assert_eq!(reference.port(), Some(443));
+        assert_eq!(reference.tag(), Some("1.0"));
+        assert_eq!(
+            reference.digest().map(ToString::to_string),
+            Some(DIGEST.to_string())
+        );
+
+        let reference = parse(&format!("app@{}", DIGEST));
+        assert_eq!(reference.tag(), None);
+        assert_eq!(
+            reference.to_string(),
+            format!("docker.io/library/app@{}", DIGEST)
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_reference() {
+        let cases = vec![
+            ("", ReferenceError::Empty),
+            (
+                "Nginx",
+                ReferenceError::InvalidRepository("Nginx".to_string()),
+            ),
+            ("nginx:", ReferenceError::InvalidTag("".to_string())),
+            (
+                "nginx:-latest",
+                ReferenceError::InvalidTag("-latest".to_string()),
+            ),
+            (
+                "example.com:http/app",
+                ReferenceError::InvalidPort("http".to_string()),
+            ),
+            (
+                "-bad.com/app",
+                ReferenceError::InvalidRegistry("-bad.com".to_string()),
+            ),
+            (
+                "example.com/app//x",
+                ReferenceError::InvalidRepository("app//x".to_string()),
+            ),
+            (
+                "example.com/a---b_-c",
+                ReferenceError::InvalidRepository("a---b_-c".to_string()),
+            ),
+            (
+                "hub.example.com/my/bundle@sha256:eeeeeeeee...",
+                ReferenceError::InvalidDigest("sha256:eeeeeeeee...".to_string()),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(input.parse::<ImageReference>(), Err(expected), "{}", input);
+        }
+
+        let long = format!("example.com/{}", "a".repeat(250));
+        assert_eq!(
+            long.parse::<ImageReference>(),
+            Err(ReferenceError::NameTooLong(262))
+        );
+    }
+
+    #[test]
+    fn test_reference_serde_round_trip() {
+        let reference = parse("technosophos/helloworld:0.1.0");
+        let json = serde_json::to_string(&reference).expect("serialized reference");
+        assert_eq!(json, r#""docker.io/technosophos/helloworld:0.1.0""#);
+        let parsed: ImageReference = serde_json::from_str(&json).expect("deserialized");
+        assert_eq!(parsed, reference);
+
+        assert!(serde_json::from_str::<ImageReference>(r#""UPPER""#).is_err());
+    }
+}
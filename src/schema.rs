@@ -0,0 +1,880 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Number, Value};
+use std::collections::BTreeMap;
+
+use crate::validation::escape;
+
+/// The number of `$ref`s that may be followed while validating a single value.
+///
+/// This stops self-referencing definitions from recursing forever.
+const MAX_REFERENCE_DEPTH: usize = 32;
+
+/// Schema describes a JSON Schema (draft-07), as used by bundle definitions.
+///
+/// The keywords that CNAB tooling commonly inspects are modeled as fields. Any other
+/// keyword (such as `allOf` or `uniqueItems`) is kept in `additional_keywords`, so a
+/// schema survives a round trip through serde without losing anything.
+///
+/// ```
+/// use libcnab::{InstanceType, Schema, SchemaType};
+///
+/// let schema: Schema = serde_json::from_str(r#"{"type": "integer", "minimum": 10}"#).unwrap();
+/// assert_eq!(schema.schema_type, Some(SchemaType::Single(InstanceType::Integer)));
+/// assert_eq!(schema.minimum.unwrap().as_u64(), Some(10));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schema {
+    /// A comment for schema maintainers
+    #[serde(rename = "$comment", skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// The identifier of this schema
+    #[serde(rename = "$id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// A reference to another schema, such as `#/definitions/port`
+    #[serde(rename = "$ref", skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// The schema for object properties not named in `properties`, or `false` to forbid them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_properties: Option<SchemaOrBool>,
+    /// The only value that is valid
+    #[serde(
+        rename = "const",
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub const_value: Option<Value>,
+    /// The encoding used to store binary data in a string, such as `base64`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+    /// The media type of the contents of a string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_media_type: Option<String>,
+    /// The default value
+    ///
+    /// An explicit `null` default is `Some(Value::Null)`, while a missing default is `None`.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default: Option<Value>,
+    /// Named subschemas that may be referenced with `$ref`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definitions: Option<BTreeMap<String, SchemaOrBool>>,
+    /// A human readable description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The exhaustive list of valid values
+    #[serde(rename = "enum", skip_serializing_if = "Option::is_none")]
+    pub enum_values: Option<Vec<Value>>,
+    /// The exclusive upper bound of a number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclusive_maximum: Option<Number>,
+    /// The exclusive lower bound of a number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclusive_minimum: Option<Number>,
+    /// A semantic format for strings, such as `uri`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// The schema (or schemas, for tuples) of array items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Items>,
+    /// The maximum length of a string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u64>,
+    /// The inclusive upper bound of a number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<Number>,
+    /// The minimum length of a string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u64>,
+    /// The inclusive lower bound of a number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<Number>,
+    /// A regular expression that strings must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// The schemas of named object properties
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<BTreeMap<String, SchemaOrBool>>,
+    /// Indicates that the value is managed by the owning authority
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+    /// The object properties that must be present
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<Vec<String>>,
+    /// A short title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The type (or types) of valid values
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub schema_type: Option<SchemaType>,
+    /// Indicates that the value is sensitive and should not be shown once written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub write_only: Option<bool>,
+    /// Keywords not modeled above, kept verbatim
+    #[serde(flatten)]
+    pub additional_keywords: BTreeMap<String, Value>,
+}
+
+impl Schema {
+    /// Returns true if this schema accepts values of the given type.
+    ///
+    /// A schema without a `type` accepts every type.
+    pub fn allows_type(&self, instance_type: InstanceType) -> bool {
+        match &self.schema_type {
+            None => true,
+            Some(t) => t.contains(instance_type),
+        }
+    }
+
+    /// Interpret raw text, such as the contents of an output file, as a value of this schema.
+    ///
+    /// Text is taken verbatim when the schema only allows strings. Otherwise it is parsed
+    /// as JSON, unless that yields a type the schema rejects while strings are allowed.
+    /// The result still needs to be checked with `validate`.
+    ///
+    /// ```
+    /// use libcnab::Schema;
+    /// use serde_json::json;
+    ///
+    /// let count: Schema = serde_json::from_str(r#"{"type": "integer"}"#).unwrap();
+    /// assert_eq!(count.parse_value("3\n"), json!(3));
+    ///
+    /// let name: Schema = serde_json::from_str(r#"{"type": "string"}"#).unwrap();
+    /// assert_eq!(name.parse_value("3"), json!("3"));
+    /// ```
+    pub fn parse_value(&self, raw: &str) -> Value {
+        if self.schema_type != Some(SchemaType::Single(InstanceType::String)) {
+            if let Ok(value) = serde_json::from_str::<Value>(raw.trim()) {
+                if self.allows_type(InstanceType::of(&value))
+                    || !self.allows_type(InstanceType::String)
+                {
+                    return value;
+                }
+            }
+        }
+        Value::String(raw.to_string())
+    }
+
+    /// Validate a value against this schema.
+    ///
+    /// References of the form `#/definitions/<name>` are resolved against `definitions`,
+    /// which for parameters and outputs is `Bundle::definitions`. The keywords modeled by
+    /// `Schema` are evaluated; keywords in `additional_keywords` (such as `allOf`) are not.
+    ///
+    /// ```
+    /// use libcnab::Schema;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let schema: Schema = serde_json::from_str(r#"{"type": "integer", "maximum": 10}"#).unwrap();
+    /// assert!(schema.validate(&serde_json::json!(5), &BTreeMap::new()).is_ok());
+    ///
+    /// let errors = schema.validate(&serde_json::json!(50), &BTreeMap::new()).unwrap_err();
+    /// assert_eq!(errors[0].message, "50 is greater than the maximum of 10");
+    /// ```
+    pub fn validate(
+        &self,
+        value: &Value,
+        definitions: &BTreeMap<String, SchemaOrBool>,
+    ) -> Result<(), Vec<SchemaError>> {
+        let mut errors = Vec::new();
+        validate_at(self, value, definitions, "", 0, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate_at(
+    schema: &Schema,
+    value: &Value,
+    definitions: &BTreeMap<String, SchemaOrBool>,
+    pointer: &str,
+    depth: usize,
+    errors: &mut Vec<SchemaError>,
+) {
+    // In draft-07, keywords alongside `$ref` are ignored.
+    if let Some(reference) = &schema.reference {
+        match resolve(reference, definitions) {
+            Some(_) if depth >= MAX_REFERENCE_DEPTH => errors.push(SchemaError::new(
+                pointer,
+                format!("$ref {:?} is nested too deeply", reference),
+            )),
+            Some(target) => {
+                validate_subschema(target, value, definitions, pointer, depth + 1, errors)
+            }
+            None => errors.push(SchemaError::new(
+                pointer,
+                format!("cannot resolve $ref {:?}", reference),
+            )),
+        }
+        return;
+    }
+
+    let instance_type = InstanceType::of(value);
+    if let Some(schema_type) = &schema.schema_type {
+        if !schema_type.contains(instance_type) {
+            errors.push(SchemaError::new(
+                pointer,
+                format!("expected {}, found {}", schema_type, instance_type),
+            ));
+            return;
+        }
+    }
+    if let Some(allowed) = &schema.enum_values {
+        if !allowed.contains(value) {
+            errors.push(SchemaError::new(
+                pointer,
+                format!("{} is not one of the allowed values", value),
+            ));
+        }
+    }
+    if let Some(expected) = &schema.const_value {
+        if value != expected {
+            errors.push(SchemaError::new(
+                pointer,
+                format!("{} is not equal to {}", value, expected),
+            ));
+        }
+    }
+
+    match value {
+        Value::Number(n) => validate_number(schema, n, pointer, errors),
+        Value::String(s) => validate_string(schema, s, pointer, errors),
+        Value::Array(items) => validate_array(schema, items, definitions, pointer, depth, errors),
+        Value::Object(properties) => {
+            validate_object(schema, properties, definitions, pointer, depth, errors)
+        }
+        Value::Null | Value::Bool(_) => {}
+    }
+}
+
+/// Look up a `#/definitions/<name>` reference.
+fn resolve<'a>(
+    reference: &str,
+    definitions: &'a BTreeMap<String, SchemaOrBool>,
+) -> Option<&'a SchemaOrBool> {
+    let name = reference.strip_prefix("#/definitions/")?;
+    definitions.get(&name.replace("~1", "/").replace("~0", "~"))
+}
+
+fn validate_number(schema: &Schema, n: &Number, pointer: &str, errors: &mut Vec<SchemaError>) {
+    let x = match n.as_f64() {
+        Some(x) => x,
+        None => return,
+    };
+    let bound = |limit: &Option<Number>, fails: fn(f64, f64) -> bool, message: &str| {
+        limit
+            .as_ref()
+            .filter(|limit| limit.as_f64().is_some_and(|l| fails(x, l)))
+            .map(|limit| SchemaError::new(pointer, format!("{} is {} {}", n, message, limit)))
+    };
+    errors.extend(bound(
+        &schema.minimum,
+        |x, l| x < l,
+        "less than the minimum of",
+    ));
+    errors.extend(bound(
+        &schema.maximum,
+        |x, l| x > l,
+        "greater than the maximum of",
+    ));
+    errors.extend(bound(
+        &schema.exclusive_minimum,
+        |x, l| x <= l,
+        "not greater than the exclusive minimum of",
+    ));
+    errors.extend(bound(
+        &schema.exclusive_maximum,
+        |x, l| x >= l,
+        "not less than the exclusive maximum of",
+    ));
+}
+
+fn validate_string(schema: &Schema, s: &str, pointer: &str, errors: &mut Vec<SchemaError>) {
+    let length = s.chars().count() as u64;
+    if let Some(min) = schema.min_length {
+        if length < min {
+            errors.push(SchemaError::new(
+                pointer,
+                format!("string is shorter than the minimum length of {}", min),
+            ));
+        }
+    }
+    if let Some(max) = schema.max_length {
+        if length > max {
+            errors.push(SchemaError::new(
+                pointer,
+                format!("string is longer than the maximum length of {}", max),
+            ));
+        }
+    }
+    if let Some(pattern) = &schema.pattern {
+        match Regex::new(pattern) {
+            Ok(re) if re.is_match(s) => {}
+            Ok(_) => errors.push(SchemaError::new(
+                pointer,
+                format!("string does not match the pattern {:?}", pattern),
+            )),
+            Err(e) => errors.push(SchemaError::new(
+                pointer,
+                format!("invalid pattern {:?}: {}", pattern, e),
+            )),
+        }
+    }
+}
+
+fn validate_array(
+    schema: &Schema,
+    items: &[Value],
+    definitions: &BTreeMap<String, SchemaOrBool>,
+    pointer: &str,
+    depth: usize,
+    errors: &mut Vec<SchemaError>,
+) {
+    let item_pointer = |i: usize| format!("{}/{}", pointer, i);
+    match &schema.items {
+        Some(Items::Single(item_schema)) => {
+            for (i, item) in items.iter().enumerate() {
+                validate_subschema(
+                    item_schema,
+                    item,
+                    definitions,
+                    &item_pointer(i),
+                    depth,
+                    errors,
+                );
+            }
+        }
+        Some(Items::Tuple(item_schemas)) => {
+            for (i, (item_schema, item)) in item_schemas.iter().zip(items).enumerate() {
+                validate_subschema(
+                    item_schema,
+                    item,
+                    definitions,
+                    &item_pointer(i),
+                    depth,
+                    errors,
+                );
+            }
+        }
+        None => {}
+    }
+}
+
+fn validate_object(
+    schema: &Schema,
+    properties: &serde_json::Map<String, Value>,
+    definitions: &BTreeMap<String, SchemaOrBool>,
+    pointer: &str,
+    depth: usize,
+    errors: &mut Vec<SchemaError>,
+) {
+    if let Some(required) = &schema.required {
+        for name in required {
+            if !properties.contains_key(name) {
+                errors.push(SchemaError::new(
+                    pointer,
+                    format!("missing required property {:?}", name),
+                ));
+            }
+        }
+    }
+    for (name, value) in properties {
+        let property_pointer = format!("{}/{}", pointer, escape(name));
+        let declared = schema.properties.as_ref().and_then(|p| p.get(name));
+        match declared.or(schema.additional_properties.as_ref()) {
+            Some(SchemaOrBool::Bool(false)) => errors.push(SchemaError::new(
+                &property_pointer,
+                format!("property {:?} is not allowed", name),
+            )),
+            Some(property_schema) => validate_subschema(
+                property_schema,
+                value,
+                definitions,
+                &property_pointer,
+                depth,
+                errors,
+            ),
+            None => {}
+        }
+    }
+}
+
+/// Validate a value against a subschema that may be a boolean.
+fn validate_subschema(
+    schema: &SchemaOrBool,
+    value: &Value,
+    definitions: &BTreeMap<String, SchemaOrBool>,
+    pointer: &str,
+    depth: usize,
+    errors: &mut Vec<SchemaError>,
+) {
+    match schema {
+        SchemaOrBool::Bool(true) => {}
+        SchemaOrBool::Bool(false) => {
+            errors.push(SchemaError::new(pointer, "no value is allowed".to_string()))
+        }
+        SchemaOrBool::Schema(schema) => {
+            validate_at(schema, value, definitions, pointer, depth, errors)
+        }
+    }
+}
+
+/// SchemaError describes one way in which a value does not conform to a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    /// A JSON pointer to the offending part of the value, which is empty for the value itself
+    pub pointer: String,
+    /// A human readable explanation
+    pub message: String,
+}
+
+impl SchemaError {
+    fn new(pointer: &str, message: String) -> Self {
+        SchemaError {
+            pointer: pointer.to_string(),
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.pointer.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.pointer, self.message)
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Deserialize a present value (including `null`) as `Some`.
+///
+/// Paired with `#[serde(default)]`, this distinguishes a missing keyword from an explicit `null`.
+fn deserialize_some<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
+/// The JSON data types a schema can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceType {
+    Array,
+    Boolean,
+    Integer,
+    Null,
+    Number,
+    Object,
+    String,
+}
+
+impl InstanceType {
+    /// Returns the type of a JSON value.
+    ///
+    /// Numbers without a fractional part, such as `1.0`, are integers.
+    pub fn of(value: &Value) -> InstanceType {
+        match value {
+            Value::Null => InstanceType::Null,
+            Value::Bool(_) => InstanceType::Boolean,
+            Value::Number(n) if n.is_u64() || n.is_i64() => InstanceType::Integer,
+            Value::Number(n) if n.as_f64().is_some_and(|f| f.fract() == 0.0) => {
+                InstanceType::Integer
+            }
+            Value::Number(_) => InstanceType::Number,
+            Value::String(_) => InstanceType::String,
+            Value::Array(_) => InstanceType::Array,
+            Value::Object(_) => InstanceType::Object,
+        }
+    }
+}
+
+impl std::fmt::Display for InstanceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InstanceType::Array => "array",
+            InstanceType::Boolean => "boolean",
+            InstanceType::Integer => "integer",
+            InstanceType::Null => "null",
+            InstanceType::Number => "number",
+            InstanceType::Object => "object",
+            InstanceType::String => "string",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The value of the `type` keyword, which is either a single type or a list of types
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SchemaType {
+    Single(InstanceType),
+    Multiple(Vec<InstanceType>),
+}
+
+impl SchemaType {
+    /// Returns true if values of the given type are allowed.
+    ///
+    /// Following JSON Schema, `number` also allows integers.
+    pub fn contains(&self, instance_type: InstanceType) -> bool {
+        let allows = |t: &InstanceType| {
+            *t == instance_type
+                || (*t == InstanceType::Number && instance_type == InstanceType::Integer)
+        };
+        match self {
+            SchemaType::Single(t) => allows(t),
+            SchemaType::Multiple(types) => types.iter().any(allows),
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaType::Single(t) => write!(f, "{}", t),
+            SchemaType::Multiple(types) => {
+                let names: Vec<String> = types.iter().map(ToString::to_string).collect();
+                write!(f, "{}", names.join(" or "))
+            }
+        }
+    }
+}
+
+/// The value of the `items` keyword
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Items {
+    /// Every item must match this schema
+    Single(Box<SchemaOrBool>),
+    /// Each item must match the schema at the same position
+    Tuple(Vec<SchemaOrBool>),
+}
+
+/// A subschema that may also be written as a boolean, where `true` accepts everything
+/// and `false` accepts nothing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SchemaOrBool {
+    Bool(bool),
+    Schema(Box<Schema>),
+}
+
+impl SchemaOrBool {
+    /// The schema, unless this is a boolean.
+    pub fn as_schema(&self) -> Option<&Schema> {
+        match self {
+            SchemaOrBool::Bool(_) => None,
+            SchemaOrBool::Schema(schema) => Some(schema),
+        }
+    }
+
+    /// Returns true if this subschema accepts values of the given type.
+    pub fn allows_type(&self, instance_type: InstanceType) -> bool {
+        match self {
+            SchemaOrBool::Bool(allowed) => *allowed,
+            SchemaOrBool::Schema(schema) => schema.allows_type(instance_type),
+        }
+    }
+
+    /// Interpret raw text as a value of this subschema. See `Schema::parse_value`.
+    ///
+    /// A boolean subschema treats the text like a schema without a `type`.
+    pub fn parse_value(&self, raw: &str) -> Value {
+        match self {
+            SchemaOrBool::Bool(_) => Schema::default().parse_value(raw),
+            SchemaOrBool::Schema(schema) => schema.parse_value(raw),
+        }
+    }
+
+    /// Follow `$ref`s from this subschema until reaching a boolean, or a schema without a
+    /// reference.
+    ///
+    /// Returns `None` if a reference cannot be resolved.
+    pub(crate) fn dereference<'a>(
+        &'a self,
+        definitions: &'a BTreeMap<String, SchemaOrBool>,
+    ) -> Option<&'a SchemaOrBool> {
+        let mut subschema = self;
+        for _ in 0..MAX_REFERENCE_DEPTH {
+            match subschema {
+                SchemaOrBool::Schema(schema) => match &schema.reference {
+                    Some(reference) => subschema = resolve(reference, definitions)?,
+                    None => return Some(subschema),
+                },
+                SchemaOrBool::Bool(_) => return Some(subschema),
+            }
+        }
+        None
+    }
+
+    /// Validate a value against this subschema. See `Schema::validate`.
+    pub fn validate(
+        &self,
+        value: &Value,
+        definitions: &BTreeMap<String, SchemaOrBool>,
+    ) -> Result<(), Vec<SchemaError>> {
+        let mut errors = Vec::new();
+        validate_subschema(self, value, definitions, "", 0, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl From<Schema> for SchemaOrBool {
+    fn from(schema: Schema) -> Self {
+        SchemaOrBool::Schema(Box::new(schema))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_fields() {
+        let schema: Schema = serde_json::from_value(json!({
+            "type": ["string", "null"],
+            "enum": ["a", "b", null],
+            "default": "a",
+            "minLength": 1,
+            "maxLength": 3,
+            "pattern": "^[a-z]+$",
+            "writeOnly": true,
+            "contentEncoding": "base64"
+        }))
+        .expect("parsed schema");
+
+        assert_eq!(
+            schema.schema_type,
+            Some(SchemaType::Multiple(vec![
+                InstanceType::String,
+                InstanceType::Null
+            ]))
+        );
+        assert!(schema.allows_type(InstanceType::Null));
+        assert!(!schema.allows_type(InstanceType::Integer));
+        assert_eq!(schema.enum_values.as_ref().map(Vec::len), Some(3));
+        assert_eq!(schema.default, Some(json!("a")));
+        assert_eq!(schema.min_length, Some(1));
+        assert_eq!(schema.max_length, Some(3));
+        assert_eq!(schema.pattern, Some("^[a-z]+$".to_string()));
+        assert_eq!(schema.write_only, Some(true));
+        assert_eq!(schema.content_encoding, Some("base64".to_string()));
+    }
+
+    #[test]
+    fn test_schema_null_default() {
+        let schema: Schema = serde_json::from_value(json!({"default": null})).unwrap();
+        assert_eq!(schema.default, Some(Value::Null));
+
+        let schema: Schema = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(schema.default, None);
+    }
+
+    fn schema(value: Value) -> Schema {
+        serde_json::from_value(value).expect("parsed schema")
+    }
+
+    fn messages(result: Result<(), Vec<SchemaError>>) -> Vec<String> {
+        result
+            .err()
+            .unwrap_or_default()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_scalars() {
+        let defs = BTreeMap::new();
+        let port = schema(json!({"type": "integer", "minimum": 1, "exclusiveMaximum": 65536}));
+        assert_eq!(port.validate(&json!(80), &defs), Ok(()));
+        assert_eq!(port.validate(&json!(80.0), &defs), Ok(()));
+        assert_eq!(
+            messages(port.validate(&json!(0), &defs)),
+            vec!["0 is less than the minimum of 1"]
+        );
+        assert_eq!(
+            messages(port.validate(&json!(65536), &defs)),
+            vec!["65536 is not less than the exclusive maximum of 65536"]
+        );
+        assert_eq!(
+            messages(port.validate(&json!("80"), &defs)),
+            vec!["expected integer, found string"]
+        );
+
+        let name = schema(json!({
+            "type": "string",
+            "minLength": 2,
+            "maxLength": 4,
+            "pattern": "^[a-z]+$"
+        }));
+        assert_eq!(name.validate(&json!("abc"), &defs), Ok(()));
+        assert_eq!(
+            messages(name.validate(&json!("ABCDE"), &defs)),
+            vec![
+                "string is longer than the maximum length of 4",
+                "string does not match the pattern \"^[a-z]+$\"",
+            ]
+        );
+
+        let color = schema(json!({"type": ["string", "null"], "enum": ["red", "blue", null]}));
+        assert_eq!(color.validate(&Value::Null, &defs), Ok(()));
+        assert_eq!(
+            messages(color.validate(&json!("green"), &defs)),
+            vec!["\"green\" is not one of the allowed values"]
+        );
+    }
+
+    #[test]
+    fn test_validate_structures() {
+        let mut defs = BTreeMap::new();
+        defs.insert(
+            "port".to_string(),
+            schema(json!({"type": "integer"})).into(),
+        );
+
+        let server = schema(json!({
+            "type": "object",
+            "required": ["host", "ports"],
+            "properties": {
+                "host": {"type": "string"},
+                "ports": {"type": "array", "items": {"$ref": "#/definitions/port"}}
+            },
+            "additionalProperties": false
+        }));
+        assert_eq!(
+            server.validate(&json!({"host": "example.com", "ports": [80, 443]}), &defs),
+            Ok(())
+        );
+        assert_eq!(
+            messages(server.validate(&json!({"ports": [80, "443"], "tls": true}), &defs)),
+            vec![
+                "missing required property \"host\"",
+                "/ports/1: expected integer, found string",
+                "/tls: property \"tls\" is not allowed",
+            ]
+        );
+
+        let dangling = schema(json!({"$ref": "#/definitions/nope"}));
+        assert_eq!(
+            messages(dangling.validate(&json!(1), &defs)),
+            vec!["cannot resolve $ref \"#/definitions/nope\""]
+        );
+    }
+
+    #[test]
+    fn test_validate_reference_cycle() {
+        let mut defs: BTreeMap<String, SchemaOrBool> = BTreeMap::new();
+        defs.insert(
+            "loop".to_string(),
+            schema(json!({"$ref": "#/definitions/loop"})).into(),
+        );
+        let errors = defs["loop"].validate(&json!(1), &defs).expect_err("cycle");
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_schema_round_trip() {
+        let original = json!({
+            "$comment": "a port",
+            "$ref": "#/definitions/port",
+            "additionalProperties": false,
+            "allOf": [{"minimum": 1}],
+            "const": null,
+            "default": null,
+            "definitions": {
+                "port": {"type": "integer", "minimum": 10, "maximum": 10240}
+            },
+            "exclusiveMinimum": 1.5,
+            "items": [{"type": "string"}, {"$ref": "#/definitions/port"}],
+            "properties": {
+                "host": {
+                    "type": "string",
+                    "format": "hostname",
+                    "items": {"type": "number"}
+                }
+            },
+            "readOnly": false,
+            "required": ["host"],
+            "title": "Port",
+            "type": "object",
+            "uniqueItems": true,
+            "x-vendor": {"nested": [1, 2, 3]}
+        });
+
+        let schema: Schema = serde_json::from_value(original.clone()).expect("parsed schema");
+        assert_eq!(schema.additional_keywords.len(), 3);
+        assert_eq!(
+            schema.additional_properties,
+            Some(SchemaOrBool::Bool(false))
+        );
+
+        let round_tripped = serde_json::to_value(&schema).expect("serialized schema");
+        assert_eq!(round_tripped, original);
+    }
+
+    #[test]
+    fn test_boolean_subschemas_round_trip() {
+        for original in &[
+            json!({"definitions": {"anything": true, "nothing": false}}),
+            json!({"properties": {"x": true, "y": false, "z": {"type": "string"}}}),
+            json!({"items": false}),
+            json!({"items": true}),
+            json!({"items": [true, {"type": "integer"}, false]}),
+            json!({"additionalProperties": true}),
+        ] {
+            let schema: Schema = serde_json::from_value(original.clone()).expect("parsed schema");
+            assert!(schema.additional_keywords.is_empty(), "{}", original);
+            let round_tripped = serde_json::to_value(&schema).expect("serialized schema");
+            assert_eq!(&round_tripped, original);
+        }
+
+        let schema: Schema = serde_json::from_value(json!({"items": false})).unwrap();
+        assert_eq!(
+            schema.items,
+            Some(Items::Single(Box::new(SchemaOrBool::Bool(false))))
+        );
+    }
+
+    #[test]
+    fn test_validate_boolean_subschemas() {
+        let defs = BTreeMap::new();
+        let object = schema(json!({"properties": {"x": true, "y": false}}));
+        assert_eq!(object.validate(&json!({"x": 1, "z": 2}), &defs), Ok(()));
+        assert_eq!(
+            messages(object.validate(&json!({"y": 1}), &defs)),
+            vec!["/y: property \"y\" is not allowed"]
+        );
+
+        let tuple = schema(json!({"items": [true, false]}));
+        assert_eq!(tuple.validate(&json!([1]), &defs), Ok(()));
+        assert_eq!(
+            messages(tuple.validate(&json!([1, 2]), &defs)),
+            vec!["/1: no value is allowed"]
+        );
+
+        let mut defs = BTreeMap::new();
+        defs.insert("nothing".to_string(), SchemaOrBool::Bool(false));
+        let reference = schema(json!({"$ref": "#/definitions/nothing"}));
+        assert_eq!(
+            messages(reference.validate(&json!(1), &defs)),
+            vec!["no value is allowed"]
+        );
+    }
+}
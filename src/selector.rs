@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+
+use crate::cnab::{Bundle, InvocationImage};
+
+/// The image type of an invocation image that does not declare one.
+pub const DEFAULT_IMAGE_TYPE: &str = "oci";
+
+/// The label an invocation image uses to declare its operating system.
+pub const OS_LABEL: &str = "os";
+/// The label an invocation image uses to declare its machine architecture.
+pub const ARCH_LABEL: &str = "arch";
+
+/// ImageSelector chooses the invocation image a driver should run.
+///
+/// A driver lists the image types it supports, most preferred first. The selector picks
+/// the image whose type the driver prefers most, breaking ties by the order of
+/// `Bundle::invocation_images`. An image without an `imageType` is an `oci` image.
+///
+/// Invocation images have no `platform` field, so platform constraints are matched
+/// against the `os` and `arch` labels. An image without one of those labels runs on any
+/// platform. Other label constraints must match exactly.
+///
+/// ```
+/// use libcnab::{Bundle, ImageSelector};
+///
+/// let bundle: Bundle = r#"{
+///     "name": "aristotle",
+///     "invocationImages": [
+///         {"image": "technosophos/aristotle:1.0.0-arm64", "labels": {"arch": "arm64"}},
+///         {"image": "technosophos/aristotle:1.0.0", "labels": {"arch": "amd64"}}
+///     ],
+///     "schemaVersion": "v1.0.0",
+///     "version": "1.0.0"
+/// }"#.parse().unwrap();
+///
+/// let image = ImageSelector::new(&["docker", "oci"])
+///     .os("linux")
+///     .arch("amd64")
+///     .select(&bundle)
+///     .unwrap();
+/// assert_eq!(image.image, "technosophos/aristotle:1.0.0");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ImageSelector {
+    image_types: Vec<String>,
+    labels: BTreeMap<String, String>,
+}
+
+impl ImageSelector {
+    /// Start a selector for a driver that supports the given image types, most preferred
+    /// first.
+    pub fn new<S: AsRef<str>>(image_types: &[S]) -> Self {
+        ImageSelector {
+            image_types: image_types.iter().map(|t| t.as_ref().to_string()).collect(),
+            labels: BTreeMap::new(),
+        }
+    }
+
+    /// Require images that run on the given operating system.
+    pub fn os<S: Into<String>>(self, os: S) -> Self {
+        self.label(OS_LABEL, os)
+    }
+
+    /// Require images that run on the given machine architecture.
+    pub fn arch<S: Into<String>>(self, arch: S) -> Self {
+        self.label(ARCH_LABEL, arch)
+    }
+
+    /// Require images that carry the given label.
+    pub fn label<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Choose an invocation image from the bundle.
+    ///
+    /// If no image is acceptable, the error explains why each one was rejected.
+    pub fn select<'a>(&self, bundle: &'a Bundle) -> Result<&'a InvocationImage, SelectionError> {
+        let mut best: Option<(usize, &InvocationImage)> = None;
+        let mut rejected = Vec::new();
+        for (index, image) in bundle.invocation_images.iter().enumerate() {
+            match self.rank(image) {
+                Ok(rank) => match best {
+                    Some((best_rank, _)) if best_rank <= rank => {}
+                    _ => best = Some((rank, image)),
+                },
+                Err(reasons) => rejected.push(RejectedImage {
+                    index,
+                    image: image.image.clone(),
+                    reasons,
+                }),
+            }
+        }
+        match best {
+            Some((_, image)) => Ok(image),
+            None if rejected.is_empty() => Err(SelectionError::NoInvocationImages),
+            None => Err(SelectionError::NoMatch(rejected)),
+        }
+    }
+
+    /// The driver's preference for an image, lower being better, or the reasons the
+    /// image cannot be used.
+    fn rank(&self, image: &InvocationImage) -> Result<usize, Vec<RejectionReason>> {
+        let image_type = image.image_type.as_deref().unwrap_or(DEFAULT_IMAGE_TYPE);
+        let rank = self.image_types.iter().position(|t| t == image_type);
+
+        let mut reasons = Vec::new();
+        if rank.is_none() {
+            reasons.push(RejectionReason::UnsupportedImageType(
+                image_type.to_string(),
+            ));
+        }
+        let no_labels = BTreeMap::new();
+        let labels = image.labels.as_ref().unwrap_or(&no_labels);
+        for (key, wanted) in &self.labels {
+            let found = labels.get(key);
+            let platform = key == OS_LABEL || key == ARCH_LABEL;
+            if found == Some(wanted) || (platform && found.is_none()) {
+                continue;
+            }
+            reasons.push(RejectionReason::Label {
+                key: key.clone(),
+                wanted: wanted.clone(),
+                found: found.cloned(),
+            });
+        }
+
+        match rank {
+            Some(rank) if reasons.is_empty() => Ok(rank),
+            _ => Err(reasons),
+        }
+    }
+}
+
+/// Invocation image selection.
+impl Bundle {
+    /// Choose the invocation image to run with the given selector.
+    ///
+    /// See `ImageSelector`.
+    pub fn select_invocation_image(
+        &self,
+        selector: &ImageSelector,
+    ) -> Result<&InvocationImage, SelectionError> {
+        selector.select(self)
+    }
+}
+
+/// SelectionError describes why no invocation image could be chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionError {
+    /// The bundle has no invocation images
+    NoInvocationImages,
+    /// Every invocation image was rejected
+    NoMatch(Vec<RejectedImage>),
+}
+
+impl std::fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionError::NoInvocationImages => write!(f, "bundle has no invocation images"),
+            SelectionError::NoMatch(rejected) => {
+                write!(f, "no suitable invocation image")?;
+                for image in rejected {
+                    write!(f, "; {}", image)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
+/// RejectedImage is an invocation image that a selector could not use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedImage {
+    /// The position of the image in `Bundle::invocation_images`
+    pub index: usize,
+    /// The image reference
+    pub image: String,
+    /// Why the image was rejected
+    pub reasons: Vec<RejectionReason>,
+}
+
+impl std::fmt::Display for RejectedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reasons: Vec<String> = self.reasons.iter().map(ToString::to_string).collect();
+        write!(f, "{}: {}", self.image, reasons.join(", "))
+    }
+}
+
+/// The reasons an invocation image can be rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum RejectionReason {
+    /// The driver does not support the image type
+    UnsupportedImageType(String),
+    /// A label is missing or has a different value
+    Label {
+        key: String,
+        wanted: String,
+        found: Option<String>,
+    },
+}
+
+impl std::fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectionReason::UnsupportedImageType(image_type) => {
+                write!(f, "image type {:?} is not supported", image_type)
+            }
+            RejectionReason::Label {
+                key,
+                wanted,
+                found: Some(found),
+            } => write!(f, "label {:?} is {:?}, not {:?}", key, found, wanted),
+            RejectionReason::Label {
+                key,
+                wanted,
+                found: None,
+            } => write!(f, "label {:?} is not {:?}", key, wanted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bundle() -> Bundle {
+        r#"{
+            "name": "aristotle",
+            "invocationImages": [
+                {"image": "aristotle.qcow2", "imageType": "qcow"},
+                {"image": "aristotle:windows", "labels": {"os": "windows"}},
+                {"image": "aristotle:linux", "imageType": "docker", "labels": {"os": "linux", "tier": "gpu"}},
+                {"image": "aristotle:any"}
+            ],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0"
+        }"#
+        .parse()
+        .expect("parsed bundle")
+    }
+
+    #[test]
+    fn test_select_by_image_type() {
+        let bun = bundle();
+        let image = ImageSelector::new(&["oci"]).select(&bun).expect("image");
+        assert_eq!(image.image, "aristotle:windows");
+
+        let image = ImageSelector::new(&["docker", "oci"])
+            .select(&bun)
+            .expect("image");
+        assert_eq!(image.image, "aristotle:linux");
+
+        let image = ImageSelector::new(&["oci", "qcow"])
+            .os("linux")
+            .select(&bun)
+            .expect("image");
+        assert_eq!(image.image, "aristotle:any");
+    }
+
+    #[test]
+    fn test_select_by_label() {
+        let bun = bundle();
+        let selector = ImageSelector::new(&["oci", "docker"])
+            .os("linux")
+            .label("tier", "gpu");
+        let image = bun.select_invocation_image(&selector).expect("image");
+        assert_eq!(image.image, "aristotle:linux");
+    }
+
+    #[test]
+    fn test_selection_errors() {
+        let bun = bundle();
+        let err = ImageSelector::new(&["oci"])
+            .os("darwin")
+            .label("tier", "gpu")
+            .select(&bun)
+            .unwrap_err();
+        let rejected = match &err {
+            SelectionError::NoMatch(rejected) => rejected,
+            other => panic!("expected no match, got {:?}", other),
+        };
+        assert_eq!(rejected.len(), 4);
+        assert_eq!(
+            rejected[0].reasons,
+            vec![
+                RejectionReason::UnsupportedImageType("qcow".to_string()),
+                RejectionReason::Label {
+                    key: "tier".to_string(),
+                    wanted: "gpu".to_string(),
+                    found: None,
+                },
+            ]
+        );
+        assert_eq!(
+            rejected[1].to_string(),
+            r#"aristotle:windows: label "os" is "windows", not "darwin", label "tier" is not "gpu""#
+        );
+        assert_eq!(rejected[3].index, 3);
+
+        let mut bun = bun;
+        bun.invocation_images.clear();
+        assert_eq!(
+            ImageSelector::new(&["oci"]).select(&bun),
+            Err(SelectionError::NoInvocationImages)
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use jsonschema::{Draft, JSONSchema};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::schema::{Schema, SchemaError};
+
+/// The JSON Schema for bundle descriptors, from CNAB Core 1.0.
+pub const BUNDLE_SCHEMA: &str = include_str!("../schemas/bundle.schema.json");
+/// The JSON Schema for claims, from CNAB Claims 1.0.
+pub const CLAIM_SCHEMA: &str = include_str!("../schemas/claim.schema.json");
+/// The JSON Schema for credential sets, from CNAB Core 1.0.
+pub const CREDENTIAL_SET_SCHEMA: &str = include_str!("../schemas/credential-set.schema.json");
+
+/// SpecSchema identifies one of the JSON Schemas published with the CNAB specifications.
+///
+/// Validating a raw document against its schema, before deserializing it, checks it
+/// against the specification itself rather than against the structs in this crate.
+///
+/// ```
+/// use libcnab::SpecSchema;
+///
+/// let document = serde_json::json!({
+///     "name": "aristotle",
+///     "invocationImages": [{"image": "technosophos/aristotle:1.0.0", "imageType": "docker"}],
+///     "schemaVersion": "v1.0.0",
+///     "version": "1.0.0",
+///     "parameters": {
+///         "port": {"destination": {"env": "PORT"}}
+///     }
+/// });
+///
+/// let errors = SpecSchema::Bundle.validate(&document).unwrap_err();
+/// assert_eq!(errors[0].pointer, "/parameters/port");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecSchema {
+    /// `bundle.schema.json`
+    Bundle,
+    /// `claim.schema.json`
+    Claim,
+    /// `credential-set.schema.json`
+    CredentialSet,
+}
+
+impl SpecSchema {
+    /// The text of the schema.
+    pub fn source(self) -> &'static str {
+        match self {
+            SpecSchema::Bundle => BUNDLE_SCHEMA,
+            SpecSchema::Claim => CLAIM_SCHEMA,
+            SpecSchema::CredentialSet => CREDENTIAL_SET_SCHEMA,
+        }
+    }
+
+    /// The schema, parsed.
+    pub fn schema(self) -> Schema {
+        parse(self.source())
+    }
+
+    /// Validate a raw document against the schema.
+    ///
+    /// Every violation of a draft-07 keyword is returned, with a JSON pointer into
+    /// `document`. References to other schemas, such as the bundle embedded in a claim,
+    /// are resolved against the embedded schemas by `$id`.
+    pub fn validate(self, document: &Value) -> Result<(), Vec<SchemaError>> {
+        self.compiled().validate(document).map_err(|errors| {
+            errors
+                .map(|error| SchemaError {
+                    pointer: error.instance_path.to_string(),
+                    message: error.to_string(),
+                })
+                .collect()
+        })
+    }
+
+    /// The schema, compiled the first time it is used.
+    fn compiled(self) -> &'static JSONSchema {
+        static BUNDLE: OnceLock<JSONSchema> = OnceLock::new();
+        static CLAIM: OnceLock<JSONSchema> = OnceLock::new();
+        static CREDENTIAL_SET: OnceLock<JSONSchema> = OnceLock::new();
+
+        let compiled = match self {
+            SpecSchema::Bundle => &BUNDLE,
+            SpecSchema::Claim => &CLAIM,
+            SpecSchema::CredentialSet => &CREDENTIAL_SET,
+        };
+        compiled.get_or_init(|| compile(self.source()))
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(source: &str) -> T {
+    serde_json::from_str(source).expect("embedded schemas are valid")
+}
+
+fn compile(source: &str) -> JSONSchema {
+    let mut options = JSONSchema::options();
+    options
+        .with_draft(Draft::Draft7)
+        .should_validate_formats(true);
+    for source in &[BUNDLE_SCHEMA, CLAIM_SCHEMA, CREDENTIAL_SET_SCHEMA] {
+        let document: Value = parse(source);
+        let id = document["$id"]
+            .as_str()
+            .expect("embedded schemas have an $id")
+            .to_string();
+        options.with_document(id, document);
+    }
+    let schema: Value = parse(source);
+    options.compile(&schema).expect("embedded schemas compile")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::fs::File;
+
+    fn testdata(path: &str) -> Value {
+        let file = File::open(path).expect("test data");
+        serde_json::from_reader(file).expect("test data JSON")
+    }
+
+    fn found(errors: &[SchemaError]) -> Vec<&str> {
+        let mut pointers: Vec<&str> = errors.iter().map(|e| e.pointer.as_str()).collect();
+        pointers.sort_unstable();
+        pointers.dedup();
+        pointers
+    }
+
+    #[test]
+    fn test_spec_schemas_parse() {
+        for schema in &[
+            SpecSchema::Bundle,
+            SpecSchema::Claim,
+            SpecSchema::CredentialSet,
+        ] {
+            assert!(schema.schema().id.is_some());
+            schema.compiled();
+        }
+    }
+
+    #[test]
+    fn test_validate_bundle_documents() {
+        for path in &[
+            "testdata/spec/hello-world.json",
+            "testdata/spec/all-fields.json",
+        ] {
+            assert_eq!(SpecSchema::Bundle.validate(&testdata(path)), Ok(()));
+        }
+
+        // The pre-1.0 parameter fields of this bundle are not in the specification.
+        let errors = SpecSchema::Bundle
+            .validate(&testdata("testdata/bundle.json"))
+            .unwrap_err();
+        assert!(found(&errors).contains(&"/parameters/backend_port"));
+
+        let errors = SpecSchema::Bundle
+            .validate(&json!({
+                "name": "aristotle",
+                "invocationImages": [{"image": "aristotle", "size": "1"}],
+                "version": "1.0.0",
+                "outputs": {"address": {"definition": "address"}}
+            }))
+            .unwrap_err();
+        assert_eq!(
+            found(&errors),
+            vec![
+                "",
+                "/invocationImages/0",
+                "/invocationImages/0/size",
+                "/outputs/address",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_empty_invocation_images() {
+        let errors = SpecSchema::Bundle
+            .validate(&json!({
+                "name": "aristotle",
+                "invocationImages": [],
+                "schemaVersion": "v1.0.0",
+                "version": "1.0.0"
+            }))
+            .unwrap_err();
+        assert_eq!(found(&errors), vec!["/invocationImages"]);
+    }
+
+    #[test]
+    fn test_validate_bundle_definitions() {
+        // Definitions are checked against the draft-07 meta-schema.
+        let errors = SpecSchema::Bundle
+            .validate(&json!({
+                "name": "aristotle",
+                "invocationImages": [{"image": "aristotle", "imageType": "oci"}],
+                "schemaVersion": "v1.0.0",
+                "version": "1.0.0",
+                "definitions": {
+                    "anything": true,
+                    "port": {"type": "integer", "minimum": "10"}
+                }
+            }))
+            .unwrap_err();
+        assert_eq!(found(&errors), vec!["/definitions/port/minimum"]);
+    }
+
+    #[test]
+    fn test_validate_claim_and_credential_set() {
+        let claim = json!({
+            "name": "hello",
+            "bundle": {
+                "name": "aristotle",
+                "invocationImages": [{"image": "aristotle"}],
+                "schemaVersion": "v1.0.0",
+                "version": "1.0.0"
+            },
+            "created": "yesterday",
+            "modified": "2018-08-30T20:39:55.549002887-06:00",
+            "result": {"action": "install", "status": "finished"},
+            "revision": "01CP6XM0KVB9V1BQDZ9NK8VP29"
+        });
+        let errors = SpecSchema::Claim.validate(&claim).unwrap_err();
+        assert_eq!(
+            found(&errors),
+            vec!["/bundle/invocationImages/0", "/created", "/result/status"]
+        );
+
+        let credentials = json!({
+            "name": "test_credentials",
+            "credentials": [
+                {"name": "kubeconfig", "source": {"path": "$HOME/.kube/config"}},
+                {"name": "token"},
+                {"name": "both", "source": {"env": "TOKEN", "value": "secret"}}
+            ]
+        });
+        let errors = SpecSchema::CredentialSet
+            .validate(&credentials)
+            .unwrap_err();
+        assert_eq!(
+            found(&errors),
+            vec!["/credentials/1", "/credentials/2/source"]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::cnab::{Bundle, BundleParseError};
+use crate::validation::pointer;
+
+/// Strict deserialization.
+impl Bundle {
+    /// Deserialize a `Bundle` from any type implementing `Read`, rejecting any field that
+    /// the CNAB Core 1.0 specification does not define.
+    ///
+    /// The bundle is deserialized losslessly, and every field left in the `unknown_fields`
+    /// of the bundle, or of a struct within it, is reported by its JSON pointer. The
+    /// contents of `custom` and of `definitions` are not checked, since both may hold
+    /// arbitrary keys. A misspelled required field is reported as missing.
+    ///
+    /// Only the structure of the document is checked. Call `validate` on the result to
+    /// check it against the rest of the specification.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, BundleParseError};
+    ///
+    /// let json = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "keyword": ["philosophy"],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0"
+    /// }"#;
+    /// match Bundle::from_json_strict(json.as_bytes()) {
+    ///     Err(BundleParseError::UnknownFields(fields)) => assert_eq!(fields, vec!["/keyword"]),
+    ///     other => panic!("unexpected result: {:?}", other),
+    /// }
+    /// ```
+    pub fn from_json_strict<R: Read>(reader: R) -> Result<Self, BundleParseError> {
+        let bundle = Self::from_json_lossless(reader)?;
+        let unknown = unknown_fields(&bundle);
+        if unknown.is_empty() {
+            Ok(bundle)
+        } else {
+            Err(BundleParseError::UnknownFields(unknown))
+        }
+    }
+
+    /// A convenience function to open and strictly deserialize a `bundle.json` file.
+    pub fn from_file_strict<P: AsRef<Path>>(path: P) -> Result<Self, BundleParseError> {
+        let file = File::open(path)?;
+        Self::from_json_strict(file)
+    }
+}
+
+/// Returns the JSON pointers of the fields in a losslessly deserialized bundle that the
+/// specification does not define.
+fn unknown_fields(bundle: &Bundle) -> Vec<String> {
+    let mut unknown = Vec::new();
+    record(&[], &bundle.unknown_fields, &mut unknown);
+
+    for (i, maintainer) in bundle.maintainers.iter().flatten().enumerate() {
+        let i = i.to_string();
+        record(
+            &["maintainers", &i],
+            &maintainer.unknown_fields,
+            &mut unknown,
+        );
+    }
+    for (name, image) in bundle.images.iter().flatten() {
+        record(&["images", name], &image.unknown_fields, &mut unknown);
+        if let Some(platform) = &image.platform {
+            record(
+                &["images", name, "platform"],
+                &platform.unknown_fields,
+                &mut unknown,
+            );
+        }
+    }
+    for (i, image) in bundle.invocation_images.iter().enumerate() {
+        let i = i.to_string();
+        record(
+            &["invocationImages", &i],
+            &image.unknown_fields,
+            &mut unknown,
+        );
+    }
+    for (name, credential) in bundle.credentials.iter().flatten() {
+        record(
+            &["credentials", name],
+            &credential.unknown_fields,
+            &mut unknown,
+        );
+    }
+    for (name, parameter) in bundle.parameters.iter().flatten() {
+        record(
+            &["parameters", name],
+            &parameter.unknown_fields,
+            &mut unknown,
+        );
+        record(
+            &["parameters", name, "destination"],
+            &parameter.destination.unknown_fields,
+            &mut unknown,
+        );
+    }
+    for (name, output) in bundle.outputs.iter().flatten() {
+        record(&["outputs", name], &output.unknown_fields, &mut unknown);
+    }
+    for (name, action) in bundle.actions.iter().flatten() {
+        record(
+            &["actions", name.as_str()],
+            &action.unknown_fields,
+            &mut unknown,
+        );
+    }
+
+    unknown
+}
+
+/// Record the pointer of each of the unknown `fields` of the struct at `path`.
+fn record(path: &[&str], fields: &BTreeMap<String, Value>, unknown: &mut Vec<String>) {
+    for field in fields.keys() {
+        let mut path = path.to_vec();
+        path.push(field);
+        unknown.push(pointer(&path));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strict_accepts_core_bundle() {
+        let bun = Bundle::from_file_strict("testdata/bundle.json");
+        assert!(bun.is_err(), "testdata/bundle.json uses pre-1.0 fields");
+
+        let json = r#"{
+            "name": "aristotle",
+            "invocationImages": [{"image": "technosophos/aristotle:1.0.0"}],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "custom": {"com.example": {"anything": true}},
+            "definitions": {"port": {"type": "integer", "x-widget": "slider"}},
+            "parameters": {
+                "port": {"definition": "port", "destination": {"env": "PORT"}}
+            }
+        }"#;
+        let bun = Bundle::from_json_strict(json.as_bytes()).expect("strict bundle");
+        assert_eq!(bun.name, "aristotle");
+    }
+
+    #[test]
+    fn test_strict_reports_unknown_fields() {
+        let json = r#"{
+            "name": "aristotle",
+            "invocationImages": [{"image": "aristotle", "imagetype": "docker"}],
+            "keyword": ["philosophy"],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "maintainers": [{"name": "Plato", "emial": "plato@example.com"}],
+            "images": {
+                "web": {"image": "nginx", "platform": {"os": "linux", "arhc": "amd64"}}
+            },
+            "credentials": {"token": {"env": "TOKEN", "requried": true}},
+            "parameters": {
+                "a/b": {"destination": {"env": "AB", "file": "/tmp/ab"}, "default": 1}
+            },
+            "outputs": {"log": {"definition": "text", "applyto": ["install"]}},
+            "actions": {"status": {"stateles": true}}
+        }"#;
+
+        match Bundle::from_json_strict(json.as_bytes()) {
+            Err(BundleParseError::UnknownFields(fields)) => assert_eq!(
+                fields,
+                vec![
+                    "/keyword",
+                    "/maintainers/0/emial",
+                    "/images/web/platform/arhc",
+                    "/invocationImages/0/imagetype",
+                    "/credentials/token/requried",
+                    "/parameters/a~1b/default",
+                    "/parameters/a~1b/destination/file",
+                    "/outputs/log/applyto",
+                    "/actions/status/stateles",
+                ]
+            ),
+            other => panic!("expected unknown fields, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_strict_does_not_validate() {
+        let json = r#"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0"
+        }"#;
+
+        let bun = Bundle::from_json_strict(json.as_bytes()).expect("strictly parsed bundle");
+        let errors = bun.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pointer, "/invocationImages");
+    }
+}
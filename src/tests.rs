@@ -1,6 +1,9 @@
+use crate::action::*;
 use crate::cnab::*;
+use crate::schema::*;
 use semver::Version;
 use spectral::prelude::*;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[test]
@@ -67,12 +70,13 @@ fn test_bundle_actions() {
     let actions = bun.actions;
     assert_that(&actions).is_some();
     let action_map = actions.unwrap();
-    let my_action = &action_map.get(&"my_action".to_string());
+    let my_action = &action_map.get("my_action");
     assert_that(&my_action.is_some());
     assert_that(&my_action.unwrap()).is_equal_to(&Action {
         description: Option::from("a custom action".to_string()),
         modifies: true,
         stateless: true,
+        unknown_fields: BTreeMap::new(),
     });
 }
 
@@ -182,7 +186,7 @@ fn test_bundle_parameters() {
             .is_equal_to("/path/to/abc".parse::<std::path::PathBuf>().unwrap());
 
         let apply_to = &arg3.unwrap().apply_to;
-        assert_that(apply_to).is_equal_to(&Some(vec!["uninstall".to_string()]));
+        assert_that(apply_to).is_equal_to(Some(vec![BundleAction::Uninstall]));
     }
 }
 
@@ -222,8 +226,9 @@ fn test_bundle_outputs() {
         .expect("outputs")
         .get("first")
         .expect("first");
-    assert_that(&first.apply_to.as_ref().expect("applyTo")[0]).is_equal_to(&"example".to_string());
-    assert_that(&first.definition).is_equal_to(&"somedef".to_string());
+    assert_that(&first.apply_to.as_ref().expect("applyTo")[0])
+        .is_equal_to(BundleAction::from("example"));
+    assert_that(&first.definition).is_equal_to("somedef".to_string());
     assert_that(&first.path.as_ref().expect("path buffer"))
         .is_equal_to(&PathBuf::from("/var/run/hello"));
     assert_that(&first.description.as_ref().expect("description"))
@@ -376,6 +381,113 @@ fn test_bundle_images() {
     }
 }
 
+// Test that content digests are parsed and validated
+#[test]
+fn test_bundle_content_digest() {
+    let bun: Bundle = r#"{
+        "name": "aristotle",
+        "invocationImages": [
+            {
+                "image": "nginx:latest",
+                "contentDigest": "sha256:a284198152c18c3e0a2f20704950ed71acfc0dc6cc660b2ebcef10f3020746bc"
+            }
+        ],
+        "schemaVersion": "1.0",
+        "version": "1.0.0"
+    }"#
+    .parse()
+    .expect("bundle is unwrapped");
+
+    let digest = bun.invocation_images[0]
+        .content_digest()
+        .expect("well-formed digest")
+        .expect("content digest");
+    assert_that(&digest.algorithm()).is_equal_to("sha256");
+    assert_that(&digest.hex())
+        .is_equal_to("a284198152c18c3e0a2f20704950ed71acfc0dc6cc660b2ebcef10f3020746bc");
+
+    // A placeholder digest does not stop the bundle from being read
+    let bun: Bundle = r#"{
+        "name": "aristotle",
+        "invocationImages": [
+            {"image": "nginx:latest", "contentDigest": "sha256:aaaaaaa..."}
+        ],
+        "schemaVersion": "v1.0.0",
+        "version": "1.0.0"
+    }"#
+    .parse()
+    .expect("bundle with a placeholder digest");
+    let image = &bun.invocation_images[0];
+    assert_that(&image.content_digest).is_equal_to(Some("sha256:aaaaaaa...".to_string()));
+    assert_that(&image.content_digest().is_err()).is_true();
+
+    let errors = bun.validate().expect_err("malformed digest");
+    assert_that(&errors[0].pointer.as_str()).is_equal_to("/invocationImages/0/contentDigest");
+}
+
+// Test that lossless parsing keeps unknown fields where they appear, and that other
+// parsing drops them
+#[test]
+fn test_bundle_lossless_round_trip() {
+    let json = r#"{
+        "name": "aristotle",
+        "com.example.owner": "lyceum",
+        "images": {
+            "web": {
+                "image": "nginx:latest",
+                "platform": {"os": "linux", "com.example.variant": "v8"}
+            }
+        },
+        "invocationImages": [
+            {"image": "technosophos/aristotle:1.0.0", "com.example.signed": true}
+        ],
+        "parameters": {
+            "port": {
+                "destination": {"env": "PORT", "com.example.mode": "0600"},
+                "com.example.ui": {"widget": "slider"}
+            }
+        },
+        "schemaVersion": "v1.0.0",
+        "version": "1.0.0"
+    }"#;
+
+    let bun = Bundle::from_json_lossless(json.as_bytes()).expect("lossless bundle");
+    assert_that(&bun.unknown_fields.get("com.example.owner"))
+        .is_equal_to(Some(&serde_json::json!("lyceum")));
+    assert_that(&bun.invocation_images[0].unknown_fields.len()).is_equal_to(1);
+    let port = &bun.parameters.as_ref().expect("parameters")["port"];
+    assert_that(&port.unknown_fields.contains_key("com.example.ui")).is_true();
+    assert_that(
+        &port
+            .destination
+            .unknown_fields
+            .contains_key("com.example.mode"),
+    )
+    .is_true();
+
+    let original: serde_json::Value = serde_json::from_str(json).expect("json");
+    let written: serde_json::Value =
+        serde_json::from_str(&bun.to_canonical_json().expect("canonical json")).expect("json");
+    assert_that(&written).is_equal_to(&original);
+
+    let lenient: Bundle = json.parse().expect("lenient bundle");
+    assert_that(&lenient.unknown_fields.is_empty()).is_true();
+    assert_that(
+        &lenient.images.as_ref().expect("images")["web"]
+            .platform
+            .as_ref()
+            .expect("platform")
+            .unknown_fields
+            .is_empty(),
+    )
+    .is_true();
+    assert_that(&lenient.digest().expect("digest")).is_not_equal_to(bun.digest().expect("digest"));
+
+    let deserialized: Bundle = serde_json::from_str(json).expect("deserialized bundle");
+    assert_that(&deserialized.unknown_fields.is_empty()).is_true();
+    assert_that(&deserialized.invocation_images[0].unknown_fields.is_empty()).is_true();
+}
+
 // Test that a parsing failure returns an error (not a panic)
 #[test]
 fn test_bundle_parse_error() {
@@ -392,8 +504,8 @@ fn test_bundle_deserialize() {
     assert_that(&bun.name).is_equal_to("helloworld".to_string());
     assert_that(&bun.schema_version).is_equal_to("v1.0.0".to_string());
     assert_that(&bun.version).is_equal_to(Version::new(0, 1, 2));
-    assert_that(&bun.maintainers.unwrap().len()).is_equal_to(&1);
-    assert_that(&bun.custom.unwrap().len()).is_equal_to(&2);
+    assert_that(&bun.maintainers.unwrap().len()).is_equal_to(1);
+    assert_that(&bun.custom.unwrap().len()).is_equal_to(2);
 }
 
 // Check that a missing file results in an error (not a panic)
@@ -402,3 +514,179 @@ fn test_bundle_from_file_not_found() {
     let bun = Bundle::from_file("no/such/file.json");
     assert_that(&bun.is_err()).is_true();
 }
+
+// Test that canonical JSON is sorted, compact, and omits unset fields
+#[test]
+fn test_bundle_canonical_json() {
+    let bun: Bundle = r#"{
+        "version": "1.0.0",
+        "schemaVersion": "v1.0.0",
+        "name": "aristotle",
+        "invocationImages": [
+            {
+                "imageType": "oci",
+                "image": "technosophos/aristotle:1.0.0"
+            }
+        ],
+        "description": "says \"hello\""
+    }"#
+    .parse()
+    .unwrap();
+
+    let json = bun.to_canonical_json().expect("canonical json");
+    assert_that(&json).is_equal_to(
+        r#"{"description":"says \"hello\"","invocationImages":[{"image":"technosophos/aristotle:1.0.0","imageType":"oci"}],"name":"aristotle","schemaVersion":"v1.0.0","version":"1.0.0"}"#
+            .to_string(),
+    );
+
+    let mut buf = Vec::new();
+    bun.write_canonical_json(&mut buf)
+        .expect("write canonical json");
+    assert_that(&String::from_utf8(buf).unwrap()).is_equal_to(&json);
+}
+
+// Test that canonical JSON survives a round trip unchanged
+#[test]
+fn test_bundle_canonical_json_round_trip() {
+    let bun = Bundle::from_file("testdata/bundle.json").expect("parse testdata/bundle.json");
+    let first = bun.to_canonical_json().expect("canonical json");
+    let second = first
+        .parse::<Bundle>()
+        .expect("reparse canonical json")
+        .to_canonical_json()
+        .expect("canonical json");
+    assert_that(&second).is_equal_to(&first);
+}
+
+// Test that definitions are parsed as typed JSON Schemata
+#[test]
+fn test_bundle_definitions() {
+    let bun: Bundle = r#"{
+        "name": "aristotle",
+        "invocationImages": [],
+        "schemaVersion": "1.0",
+        "version": "1.0.0",
+        "definitions": {
+            "port": {
+                "type": "integer",
+                "default": 8080,
+                "minimum": 1,
+                "maximum": 65535
+            },
+            "password": {
+                "type": "string",
+                "writeOnly": true
+            }
+        }
+    }"#
+    .parse()
+    .expect("parsed bundle");
+
+    let defs = bun.definitions.expect("definitions");
+    let port = defs
+        .get("port")
+        .and_then(SchemaOrBool::as_schema)
+        .expect("port");
+    assert_that(&port.schema_type).is_equal_to(Some(SchemaType::Single(InstanceType::Integer)));
+    assert_that(&port.default).is_equal_to(Some(serde_json::json!(8080)));
+    assert_that(&port.maximum.as_ref().and_then(|n| n.as_u64())).is_equal_to(Some(65535));
+
+    let password = defs
+        .get("password")
+        .and_then(SchemaOrBool::as_schema)
+        .expect("password");
+    assert_that(&password.write_only).is_equal_to(Some(true));
+}
+
+// Test that boolean definitions survive a round trip
+#[test]
+fn test_bundle_boolean_definitions() {
+    let json = r#"{"definitions":{"anything":true,"nothing":false},"invocationImages":[],"name":"aristotle","schemaVersion":"v1.0.0","version":"1.0.0"}"#;
+    let bun: Bundle = json.parse().expect("parsed bundle");
+
+    let defs = bun.definitions.as_ref().expect("definitions");
+    assert_that(&defs.get("anything")).is_equal_to(Some(&SchemaOrBool::Bool(true)));
+    assert_that(&defs.get("nothing")).is_equal_to(Some(&SchemaOrBool::Bool(false)));
+    assert_that(&bun.to_canonical_json().expect("canonical json").as_str()).is_equal_to(json);
+}
+
+// Test that parse errors report where in the document they occurred
+#[test]
+fn test_bundle_parse_error_locations() {
+    let res = r#"{
+        "name": "aristotle",
+        "invocationImages": [],
+        "schemaVersion": "v1.0.0",
+        "version": "1.0.0",
+        "parameters": {
+            "port": {"destination": "PORT"}
+        }
+    }"#
+    .parse::<Bundle>();
+    match res {
+        Err(BundleParseError::SchemaError {
+            pointer,
+            line,
+            column,
+            ..
+        }) => {
+            assert_that(&pointer.as_str()).is_equal_to("/parameters/port/destination");
+            assert_that(&line).is_equal_to(7);
+            assert_that(&(column > 0)).is_equal_to(true);
+        }
+        other => panic!("expected a schema error, got {:?}", other),
+    }
+
+    let res = Bundle::from_json(
+        r#"{
+        "name": "aristotle",
+        "invocationImages": [{"image": "nginx", "size": "large"}],
+        "schemaVersion": "v1.0.0",
+        "version": "1.0.0"
+    }"#
+        .as_bytes(),
+    );
+    match res {
+        Err(BundleParseError::SchemaError { pointer, .. }) => {
+            assert_that(&pointer.as_str()).is_equal_to("/invocationImages/0/size")
+        }
+        other => panic!("expected a schema error, got {:?}", other),
+    }
+
+    let res = "{\"name\": \"aristotle\",".parse::<Bundle>();
+    match res {
+        Err(e @ BundleParseError::SyntaxError { .. }) => {
+            assert_that(&e.to_string().starts_with("invalid JSON at line 1")).is_equal_to(true)
+        }
+        other => panic!("expected a syntax error, got {:?}", other),
+    }
+}
+
+// Test that the CNAB Core 1.0 example, and a bundle using every field, survive a round trip unchanged
+#[test]
+fn test_bundle_spec_examples() {
+    for path in &[
+        "testdata/spec/hello-world.json",
+        "testdata/spec/all-fields.json",
+    ] {
+        let file = std::fs::File::open(path).expect("example bundle");
+        let raw: serde_json::Value = serde_json::from_reader(file).expect("example JSON");
+        let bun = Bundle::from_file_strict(path).expect("strictly parsed bundle");
+
+        assert_that(&bun.to_canonical_json().expect("canonical json"))
+            .is_equal_to(crate::canonical::to_canonical_json(&raw).expect("canonical json"));
+    }
+
+    // The specification's example uses placeholder digests
+    let bun = Bundle::from_file("testdata/spec/hello-world.json").expect("parsed bundle");
+    assert_that(&bun.invocation_images[0].content_digest)
+        .is_equal_to(Some("sha256:aaaaaaa...".to_string()));
+
+    let bun = Bundle::from_file("testdata/spec/all-fields.json").expect("parsed bundle");
+    let kubeconfig = &bun.credentials.as_ref().expect("credentials")["kubeconfig"];
+    assert_that(&kubeconfig.applies_to(&BundleAction::Uninstall)).is_equal_to(false);
+    assert_that(&bun.invocation_images[0].original_image)
+        .is_equal_to(Some("example/all-fields:1.0.0".to_string()));
+    assert_that(&bun.required_extensions)
+        .is_equal_to(Some(vec!["io.cnab.dependencies".to_string()]));
+}
@@ -0,0 +1,281 @@
+use semver::Version;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::action::BundleAction;
+use crate::claim::Claim;
+use crate::cnab::{Bundle, Parameter};
+use crate::diff::BundleDiff;
+use crate::parameters::{ParameterError, ParameterErrorKind};
+
+/// Upgrade planning.
+impl Claim {
+    /// Explain what running `upgrade` with `bundle` would do to this installation.
+    ///
+    /// Parameter values stored in the claim are strings. Each is interpreted according to
+    /// its definition in the new bundle, in the same way as an output, before it is
+    /// validated.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, Claim};
+    ///
+    /// let claim: Claim = serde_json::from_str(r#"{
+    ///     "name": "hello",
+    ///     "bundle": {
+    ///         "name": "aristotle",
+    ///         "invocationImages": [],
+    ///         "schemaVersion": "v1.0.0",
+    ///         "version": "1.0.0"
+    ///     },
+    ///     "created": "2018-08-30T20:39:55.549002887-06:00",
+    ///     "modified": "2018-08-30T20:39:55.549002887-06:00",
+    ///     "parameters": {"port": "8080"},
+    ///     "result": {"action": "install", "status": "success"},
+    ///     "revision": "01CP6XM0KVB9V1BQDZ9NK8VP29"
+    /// }"#).unwrap();
+    ///
+    /// let bundle: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "2.0.0",
+    ///     "parameters": {
+    ///         "port": {"definition": "port", "destination": {"env": "PORT"}}
+    ///     },
+    ///     "definitions": {"port": {"type": "integer"}}
+    /// }"#.parse().unwrap();
+    ///
+    /// let plan = claim.plan_upgrade(&bundle);
+    /// assert_eq!(plan.carried["port"], serde_json::json!(8080));
+    /// assert!(!plan.is_downgrade());
+    /// ```
+    pub fn plan_upgrade(&self, bundle: &Bundle) -> UpgradePlan {
+        let mut plan = UpgradePlan {
+            from_version: self.bundle.version.clone(),
+            to_version: bundle.version.clone(),
+            carried: BTreeMap::new(),
+            invalid: Vec::new(),
+            dropped: Vec::new(),
+            required_parameters: Vec::new(),
+            required_credentials: Vec::new(),
+            diff: self.bundle.diff(bundle),
+        };
+
+        let no_values = BTreeMap::new();
+        let stored = self.parameters.as_ref().unwrap_or(&no_values);
+        let no_parameters = BTreeMap::new();
+        let parameters = bundle.parameters.as_ref().unwrap_or(&no_parameters);
+
+        for (name, raw) in stored {
+            match parameters.get(name) {
+                Some(parameter) if parameter.applies_to(&BundleAction::Upgrade) => {
+                    match carry_parameter(bundle, parameter, raw) {
+                        Ok(value) => {
+                            plan.carried.insert(name.clone(), value);
+                        }
+                        Err(kind) => plan.invalid.push(ParameterError {
+                            parameter: name.clone(),
+                            kind,
+                        }),
+                    }
+                }
+                _ => plan.dropped.push(name.clone()),
+            }
+        }
+
+        for (name, parameter) in parameters {
+            if parameter.applies_to(&BundleAction::Upgrade)
+                && parameter.required.unwrap_or(false)
+                && !stored.contains_key(name)
+                && bundle.parameter_default(parameter).is_none()
+            {
+                plan.required_parameters.push(name.clone());
+            }
+        }
+
+        let no_credentials = BTreeMap::new();
+        let old_credentials = self.bundle.credentials.as_ref().unwrap_or(&no_credentials);
+        for (name, credential) in bundle.credentials.iter().flatten() {
+            let was_required = old_credentials.get(name).is_some_and(|c| {
+                c.applies_to(&BundleAction::Upgrade) && c.required.unwrap_or(false)
+            });
+            if credential.applies_to(&BundleAction::Upgrade)
+                && credential.required.unwrap_or(false)
+                && !was_required
+            {
+                plan.required_credentials.push(name.clone());
+            }
+        }
+
+        plan
+    }
+}
+
+/// Interpret and validate a stored parameter value under the new bundle.
+fn carry_parameter(
+    bundle: &Bundle,
+    parameter: &Parameter,
+    raw: &str,
+) -> Result<Value, ParameterErrorKind> {
+    let definition = match bundle.parameter_definition(parameter)? {
+        Some(definition) => definition,
+        None => return Ok(Value::String(raw.to_string())),
+    };
+    let empty = BTreeMap::new();
+    let definitions = bundle.definitions.as_ref().unwrap_or(&empty);
+    let value = match definition.dereference(definitions) {
+        Some(schema) => schema.parse_value(raw),
+        // The reference cannot be resolved, which validating the value reports.
+        None => Value::String(raw.to_string()),
+    };
+    bundle.check_parameter_value(parameter, &value)?;
+    Ok(value)
+}
+
+/// UpgradePlan describes the effect of upgrading an installation to a new bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradePlan {
+    /// The version of the installed bundle
+    pub from_version: Version,
+    /// The version of the new bundle
+    pub to_version: Version,
+    /// Stored parameter values that remain valid, converted to their new types
+    pub carried: BTreeMap<String, Value>,
+    /// Stored parameter values that the new definitions reject. New values must be supplied.
+    pub invalid: Vec<ParameterError>,
+    /// Stored parameters that the new bundle does not declare for `upgrade`
+    pub dropped: Vec<String>,
+    /// Required parameters that have neither a stored value nor a default
+    pub required_parameters: Vec<String>,
+    /// Credentials that the new bundle requires for `upgrade` and the installed bundle did not
+    pub required_credentials: Vec<String>,
+    /// Every difference between the installed bundle and the new one
+    pub diff: BundleDiff,
+}
+
+impl UpgradePlan {
+    /// Returns true if the new bundle's version is lower than the installed one.
+    pub fn is_downgrade(&self) -> bool {
+        self.to_version < self.from_version
+    }
+
+    /// Returns true if the upgrade needs values that were not stored with the claim.
+    pub fn needs_input(&self) -> bool {
+        !self.invalid.is_empty()
+            || !self.required_parameters.is_empty()
+            || !self.required_credentials.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::schema::SchemaError;
+    use serde_json::json;
+
+    fn claim() -> Claim {
+        serde_json::from_str(
+            r#"{
+                "name": "hello",
+                "bundle": {
+                    "name": "aristotle",
+                    "invocationImages": [],
+                    "schemaVersion": "v1.0.0",
+                    "version": "1.2.0",
+                    "credentials": {
+                        "kubeconfig": {"path": "/root/.kube/config", "required": true},
+                        "token": {"env": "TOKEN", "required": true, "applyTo": ["install"]}
+                    }
+                },
+                "created": "2018-08-30T20:39:55.549002887-06:00",
+                "modified": "2018-08-30T20:39:55.549002887-06:00",
+                "parameters": {
+                    "port": "8080",
+                    "motd": "hello",
+                    "replicas": "zero",
+                    "legacy": "yes",
+                    "cleanup": "true"
+                },
+                "result": {"action": "install", "status": "success"},
+                "revision": "01CP6XM0KVB9V1BQDZ9NK8VP29"
+            }"#,
+        )
+        .expect("parsed claim")
+    }
+
+    fn bundle(version: &str) -> Bundle {
+        format!(
+            r##"{{
+                "name": "aristotle",
+                "invocationImages": [],
+                "schemaVersion": "v1.0.0",
+                "version": "{}",
+                "credentials": {{
+                    "kubeconfig": {{"path": "/root/.kube/config", "required": true}},
+                    "token": {{"env": "TOKEN", "required": true}},
+                    "optional": {{"env": "OPTIONAL"}},
+                    "registry": {{"env": "REGISTRY", "required": true, "applyTo": ["install"]}}
+                }},
+                "parameters": {{
+                    "port": {{"definition": "port", "destination": {{"env": "PORT"}}}},
+                    "motd": {{"destination": {{"path": "/etc/motd"}}}},
+                    "replicas": {{"definition": "count", "destination": {{"env": "REPLICAS"}}}},
+                    "cleanup": {{
+                        "applyTo": ["uninstall"],
+                        "definition": "flag",
+                        "destination": {{"env": "CLEANUP"}}
+                    }},
+                    "region": {{"definition": "text", "destination": {{"env": "REGION"}}, "required": true}},
+                    "zone": {{"definition": "zone", "destination": {{"env": "ZONE"}}, "required": true}},
+                    "site": {{"definition": "site", "destination": {{"env": "SITE"}}, "required": true}}
+                }},
+                "definitions": {{
+                    "port": {{"type": "integer", "maximum": 65535}},
+                    "count": {{"type": "integer", "minimum": 1}},
+                    "flag": {{"type": "boolean"}},
+                    "text": {{"type": "string"}},
+                    "zone": {{"type": "string", "default": "a"}},
+                    "site": {{"$ref": "#/definitions/zone"}}
+                }}
+            }}"##,
+            version
+        )
+        .parse()
+        .expect("parsed bundle")
+    }
+
+    #[test]
+    fn test_plan_upgrade() {
+        let plan = claim().plan_upgrade(&bundle("2.0.0"));
+
+        let mut carried = BTreeMap::new();
+        carried.insert("motd".to_string(), json!("hello"));
+        carried.insert("port".to_string(), json!(8080));
+        assert_eq!(plan.carried, carried);
+
+        assert_eq!(
+            plan.invalid,
+            vec![ParameterError {
+                parameter: "replicas".to_string(),
+                kind: ParameterErrorKind::Invalid(vec![SchemaError {
+                    pointer: "".to_string(),
+                    message: "expected integer, found string".to_string(),
+                }]),
+            }]
+        );
+        assert_eq!(plan.dropped, vec!["cleanup", "legacy"]);
+        assert_eq!(plan.required_parameters, vec!["region"]);
+        assert_eq!(plan.required_credentials, vec!["token"]);
+        assert_eq!(plan.diff.parameters.added.len(), 7);
+        assert!(plan.needs_input());
+        assert!(!plan.is_downgrade());
+    }
+
+    #[test]
+    fn test_plan_upgrade_downgrade() {
+        let plan = claim().plan_upgrade(&bundle("1.1.9"));
+        assert_eq!(plan.from_version, Version::new(1, 2, 0));
+        assert_eq!(plan.to_version, Version::new(1, 1, 9));
+        assert!(plan.is_downgrade());
+    }
+}
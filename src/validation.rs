@@ -0,0 +1,468 @@
+use semver::Version;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::action::BundleAction;
+use crate::cnab::{Bundle, BundleParseError};
+use crate::digest::Digest;
+
+/// Semantic validation of bundles.
+impl Bundle {
+    /// Deserialize a `Bundle` from any type implementing `Read`, and `validate` it.
+    ///
+    /// A bundle that deserializes but violates the specification is rejected with a
+    /// `BundleParseError::SemanticError` holding every violation.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, BundleParseError};
+    ///
+    /// let json = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0"
+    /// }"#;
+    /// match Bundle::from_json_validated(json.as_bytes()) {
+    ///     Err(BundleParseError::SemanticError(errors)) => {
+    ///         assert_eq!(errors[0].pointer, "/invocationImages")
+    ///     }
+    ///     other => panic!("unexpected result: {:?}", other),
+    /// }
+    /// ```
+    pub fn from_json_validated<R: Read>(reader: R) -> Result<Self, BundleParseError> {
+        let bundle = Self::from_json(reader)?;
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    /// A convenience function to open, deserialize and validate a `bundle.json` file.
+    pub fn from_file_validated<P: AsRef<Path>>(path: P) -> Result<Self, BundleParseError> {
+        let file = File::open(path)?;
+        Self::from_json_validated(file)
+    }
+
+    /// Check this bundle against the rules of CNAB Core 1.0 that deserialization alone
+    /// does not enforce.
+    ///
+    /// Every violation found is returned, not just the first.
+    ///
+    /// ```
+    /// use libcnab::{Bundle, ValidationErrorKind};
+    ///
+    /// let bundle: Bundle = r#"{
+    ///     "name": "aristotle",
+    ///     "invocationImages": [],
+    ///     "schemaVersion": "v1.0.0",
+    ///     "version": "1.0.0"
+    /// }"#.parse().unwrap();
+    ///
+    /// let errors = bundle.validate().unwrap_err();
+    /// assert_eq!(errors[0].pointer, "/invocationImages");
+    /// assert_eq!(errors[0].kind, ValidationErrorKind::MissingInvocationImages);
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        let schema_version = self
+            .schema_version
+            .strip_prefix('v')
+            .unwrap_or(&self.schema_version);
+        if Version::parse(schema_version).is_err() {
+            errors.push(ValidationError::new(
+                pointer(&["schemaVersion"]),
+                ValidationErrorKind::InvalidSchemaVersion(self.schema_version.clone()),
+            ));
+        }
+
+        if self.invocation_images.is_empty() {
+            errors.push(ValidationError::new(
+                pointer(&["invocationImages"]),
+                ValidationErrorKind::MissingInvocationImages,
+            ));
+        }
+        for (i, image) in self.invocation_images.iter().enumerate() {
+            let index = i.to_string();
+            check_content_digest(
+                &["invocationImages", index.as_str(), "contentDigest"],
+                &image.content_digest,
+                &mut errors,
+            );
+            check_size(
+                &["invocationImages", index.as_str(), "size"],
+                image.size,
+                &mut errors,
+            );
+        }
+        if let Some(images) = &self.images {
+            for (name, image) in images {
+                check_content_digest(
+                    &["images", name.as_str(), "contentDigest"],
+                    &image.content_digest,
+                    &mut errors,
+                );
+                check_size(&["images", name.as_str(), "size"], image.size, &mut errors);
+            }
+        }
+
+        if let Some(actions) = &self.actions {
+            for name in actions.keys() {
+                if name.is_built_in() {
+                    errors.push(ValidationError::new(
+                        pointer(&["actions", name.as_str()]),
+                        ValidationErrorKind::ReservedActionName(name.to_string()),
+                    ));
+                }
+            }
+        }
+
+        if let Some(credentials) = &self.credentials {
+            for (name, credential) in credentials {
+                if let Some(apply_to) = &credential.apply_to {
+                    self.check_apply_to(
+                        &["credentials", name.as_str(), "applyTo"],
+                        apply_to,
+                        &mut errors,
+                    );
+                }
+            }
+        }
+
+        if let Some(parameters) = &self.parameters {
+            for (name, parameter) in parameters {
+                match &parameter.definition {
+                    Some(definition) => self.check_definition(
+                        &["parameters", name.as_str(), "definition"],
+                        definition,
+                        &mut errors,
+                    ),
+                    None => errors.push(ValidationError::new(
+                        pointer(&["parameters", name.as_str(), "definition"]),
+                        ValidationErrorKind::MissingParameterDefinition,
+                    )),
+                }
+                if let Some(apply_to) = &parameter.apply_to {
+                    self.check_apply_to(
+                        &["parameters", name.as_str(), "applyTo"],
+                        apply_to,
+                        &mut errors,
+                    );
+                }
+            }
+        }
+
+        if let Some(outputs) = &self.outputs {
+            for (name, output) in outputs {
+                self.check_definition(
+                    &["outputs", name.as_str(), "definition"],
+                    &output.definition,
+                    &mut errors,
+                );
+                if output.path.is_none() {
+                    errors.push(ValidationError::new(
+                        pointer(&["outputs", name.as_str(), "path"]),
+                        ValidationErrorKind::MissingOutputPath,
+                    ));
+                }
+                if let Some(apply_to) = &output.apply_to {
+                    self.check_apply_to(
+                        &["outputs", name.as_str(), "applyTo"],
+                        apply_to,
+                        &mut errors,
+                    );
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check_definition(&self, path: &[&str], definition: &str, errors: &mut Vec<ValidationError>) {
+        let defined = self
+            .definitions
+            .iter()
+            .any(|definitions| definitions.contains_key(definition));
+        if !defined {
+            errors.push(ValidationError::new(
+                pointer(path),
+                ValidationErrorKind::UndefinedDefinition(definition.to_string()),
+            ));
+        }
+    }
+
+    fn check_apply_to(
+        &self,
+        path: &[&str],
+        apply_to: &[BundleAction],
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (i, action) in apply_to.iter().enumerate() {
+            if !self.has_action(action) {
+                let index = i.to_string();
+                let mut path = path.to_vec();
+                path.push(index.as_str());
+                errors.push(ValidationError::new(
+                    pointer(&path),
+                    ValidationErrorKind::UndefinedAction(action.to_string()),
+                ));
+            }
+        }
+    }
+}
+
+fn check_content_digest(path: &[&str], digest: &Option<String>, errors: &mut Vec<ValidationError>) {
+    if let Some(digest) = digest {
+        if digest.parse::<Digest>().is_err() {
+            errors.push(ValidationError::new(
+                pointer(path),
+                ValidationErrorKind::InvalidContentDigest(digest.clone()),
+            ));
+        }
+    }
+}
+
+fn check_size(path: &[&str], size: Option<i64>, errors: &mut Vec<ValidationError>) {
+    if let Some(size) = size.filter(|size| *size < 0) {
+        errors.push(ValidationError::new(
+            pointer(path),
+            ValidationErrorKind::NegativeImageSize(size),
+        ));
+    }
+}
+
+/// Build a JSON pointer (RFC 6901) from unescaped path segments.
+pub(crate) fn pointer(segments: &[&str]) -> String {
+    segments.iter().map(|s| format!("/{}", escape(s))).collect()
+}
+
+/// Escape a single JSON pointer segment.
+pub(crate) fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// ValidationError describes one way in which a bundle violates the CNAB Core 1.0 specification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// A JSON pointer to the offending value, such as `/parameters/port/definition`
+    pub pointer: String,
+    /// What is wrong with the value
+    pub kind: ValidationErrorKind,
+}
+
+impl ValidationError {
+    fn new(pointer: String, kind: ValidationErrorKind) -> Self {
+        ValidationError { pointer, kind }
+    }
+}
+
+/// The kinds of specification violation that `Bundle::validate` detects
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationErrorKind {
+    /// `schemaVersion` is not a semantic version (optionally prefixed with `v`)
+    InvalidSchemaVersion(String),
+    /// The bundle declares no invocation images
+    MissingInvocationImages,
+    /// The content digest of an image is not of the form `algorithm:hex`
+    InvalidContentDigest(String),
+    /// The size of an image is negative
+    NegativeImageSize(i64),
+    /// A parameter does not name the definition that describes it
+    MissingParameterDefinition,
+    /// An output does not say where the invocation image writes it
+    MissingOutputPath,
+    /// A definition is referenced but not present in `definitions`
+    UndefinedDefinition(String),
+    /// An `applyTo` list names an action that is neither built-in nor declared
+    UndefinedAction(String),
+    /// A custom action reuses the name of a built-in action
+    ReservedActionName(String),
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.pointer, self.kind)
+    }
+}
+
+impl std::fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationErrorKind::InvalidSchemaVersion(v) => {
+                write!(f, "schema version {:?} is not a semantic version", v)
+            }
+            ValidationErrorKind::MissingInvocationImages => {
+                write!(f, "at least one invocation image is required")
+            }
+            ValidationErrorKind::InvalidContentDigest(d) => {
+                write!(f, "content digest {:?} is malformed", d)
+            }
+            ValidationErrorKind::NegativeImageSize(s) => {
+                write!(f, "image size {} is negative", s)
+            }
+            ValidationErrorKind::MissingParameterDefinition => {
+                write!(f, "a parameter must name a definition")
+            }
+            ValidationErrorKind::MissingOutputPath => write!(f, "an output must have a path"),
+            ValidationErrorKind::UndefinedDefinition(d) => {
+                write!(f, "definition {:?} is not defined", d)
+            }
+            ValidationErrorKind::UndefinedAction(a) => write!(f, "action {:?} is not defined", a),
+            ValidationErrorKind::ReservedActionName(a) => {
+                write!(f, "custom action {:?} shadows a built-in action", a)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_valid_bundle() {
+        let bun: Bundle = r#"{
+            "name": "aristotle",
+            "invocationImages": [
+                {"image": "technosophos/aristotle:1.0.0"}
+            ],
+            "schemaVersion": "v1.0.0",
+            "version": "1.0.0",
+            "actions": {
+                "status": {"stateless": true}
+            },
+            "credentials": {
+                "kubeconfig": {"applyTo": ["install", "status"], "path": "/root/.kube/config"}
+            },
+            "parameters": {
+                "port": {
+                    "applyTo": ["install", "status"],
+                    "definition": "port",
+                    "destination": {"env": "PORT"}
+                }
+            },
+            "outputs": {
+                "address": {
+                    "applyTo": ["install", "upgrade"],
+                    "definition": "port",
+                    "path": "/cnab/app/outputs/address"
+                }
+            },
+            "definitions": {
+                "port": {"type": "integer"}
+            }
+        }"#
+        .parse()
+        .expect("parsed bundle");
+
+        assert_eq!(bun.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_from_file_validated() {
+        let bun =
+            Bundle::from_file_validated("testdata/spec/all-fields.json").expect("valid bundle");
+        assert_eq!(bun.name, "all-fields");
+
+        match Bundle::from_file_validated("testdata/bundle.json") {
+            Err(BundleParseError::SemanticError(errors)) => assert!(!errors.is_empty()),
+            other => panic!("expected semantic errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_every_violation() {
+        let bun: Bundle = r#"{
+            "name": "aristotle",
+            "invocationImages": [],
+            "schemaVersion": "1.0",
+            "version": "1.0.0",
+            "images": {
+                "web": {"image": "nginx", "contentDigest": "sha256:aaaaaaa...", "size": -1}
+            },
+            "actions": {
+                "install": {"modifies": true}
+            },
+            "credentials": {
+                "token": {"applyTo": ["status"], "env": "TOKEN"}
+            },
+            "parameters": {
+                "host": {
+                    "destination": {"env": "HOST"}
+                },
+                "port": {
+                    "applyTo": ["install", "explode"],
+                    "definition": "missing",
+                    "destination": {"env": "PORT"}
+                }
+            },
+            "outputs": {
+                "a/b": {
+                    "definition": "gone"
+                }
+            }
+        }"#
+        .parse()
+        .expect("parsed bundle");
+
+        let errors = bun.validate().expect_err("bundle is invalid");
+        let found: Vec<(&str, &ValidationErrorKind)> = errors
+            .iter()
+            .map(|e| (e.pointer.as_str(), &e.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "/schemaVersion",
+                    &ValidationErrorKind::InvalidSchemaVersion("1.0".to_string())
+                ),
+                (
+                    "/invocationImages",
+                    &ValidationErrorKind::MissingInvocationImages
+                ),
+                (
+                    "/images/web/contentDigest",
+                    &ValidationErrorKind::InvalidContentDigest("sha256:aaaaaaa...".to_string())
+                ),
+                (
+                    "/images/web/size",
+                    &ValidationErrorKind::NegativeImageSize(-1)
+                ),
+                (
+                    "/actions/install",
+                    &ValidationErrorKind::ReservedActionName("install".to_string())
+                ),
+                (
+                    "/credentials/token/applyTo/0",
+                    &ValidationErrorKind::UndefinedAction("status".to_string())
+                ),
+                (
+                    "/parameters/host/definition",
+                    &ValidationErrorKind::MissingParameterDefinition
+                ),
+                (
+                    "/parameters/port/definition",
+                    &ValidationErrorKind::UndefinedDefinition("missing".to_string())
+                ),
+                (
+                    "/parameters/port/applyTo/1",
+                    &ValidationErrorKind::UndefinedAction("explode".to_string())
+                ),
+                (
+                    "/outputs/a~1b/definition",
+                    &ValidationErrorKind::UndefinedDefinition("gone".to_string())
+                ),
+                (
+                    "/outputs/a~1b/path",
+                    &ValidationErrorKind::MissingOutputPath
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::claim::Claim;
+use crate::cnab::{self, Bundle, BundleParseError};
+use crate::credentialset::CredentialSet;
+
+/// YAML serialization, enabled by the `yaml` feature.
+///
+/// YAML documents use the same field names as the JSON form.
+impl Bundle {
+    /// Deserialize a `Bundle` from YAML read from any type implementing `Read`.
+    ///
+    /// Fields that this library does not model are discarded, as with `from_json`. The
+    /// document is read into a JSON value before it is deserialized, so errors carry a JSON
+    /// pointer, and converting the bundle to canonical JSON gives the same result as if it
+    /// had been written in JSON.
+    ///
+    /// ```
+    /// use libcnab::Bundle;
+    ///
+    /// let yaml = "
+    /// name: aristotle
+    /// version: 1.0.0
+    /// schemaVersion: v1.0.0
+    /// invocationImages:
+    ///   - image: technosophos/aristotle:1.0.0
+    /// ";
+    /// let bundle = Bundle::from_yaml(yaml.as_bytes()).unwrap();
+    /// assert_eq!(
+    ///     bundle.to_canonical_json().unwrap(),
+    ///     r#"{"invocationImages":[{"image":"technosophos/aristotle:1.0.0"}],"name":"aristotle","schemaVersion":"v1.0.0","version":"1.0.0"}"#
+    /// );
+    /// ```
+    pub fn from_yaml<R: Read>(reader: R) -> Result<Self, BundleParseError> {
+        from_reader(reader)
+    }
+
+    /// A convenience function to open and deserialize a `bundle.yaml` file.
+    pub fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<Self, BundleParseError> {
+        let file = File::open(path)?;
+        Self::from_yaml(file)
+    }
+
+    /// Serialize this bundle as YAML.
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+}
+
+impl Claim {
+    /// Deserialize a `Claim` from YAML read from any type implementing `Read`.
+    ///
+    /// Errors are reported as by `Bundle::from_yaml`, with a JSON pointer into the claim.
+    pub fn from_yaml<R: Read>(reader: R) -> Result<Self, BundleParseError> {
+        from_reader(reader)
+    }
+
+    /// Serialize this claim as YAML.
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+}
+
+impl CredentialSet {
+    /// Deserialize a `CredentialSet` from YAML read from any type implementing `Read`.
+    ///
+    /// Errors are reported as by `Bundle::from_yaml`, with a JSON pointer into the
+    /// credential set.
+    pub fn from_yaml<R: Read>(reader: R) -> Result<Self, BundleParseError> {
+        from_reader(reader)
+    }
+
+    /// Serialize this credential set as YAML.
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+}
+
+/// Read a YAML document into a JSON value, and deserialize that, so that errors carry a
+/// JSON pointer.
+fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, BundleParseError> {
+    let document: Value = serde_yaml::from_reader(reader)?;
+    let value = serde_path_to_error::deserialize(document)?;
+    Ok(value)
+}
+
+impl From<serde_yaml::Error> for BundleParseError {
+    fn from(error: serde_yaml::Error) -> Self {
+        let message = error.to_string();
+        match error.location() {
+            Some(location) => BundleParseError::SyntaxError {
+                line: location.line(),
+                column: location.column(),
+                message: cnab::without_position(&message, location.line(), location.column()),
+            },
+            None => BundleParseError::SchemaError {
+                pointer: String::new(),
+                line: 0,
+                column: 0,
+                message,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::canonical::CanonicalJSONError;
+
+    #[test]
+    fn test_bundle_yaml_canonical_json() {
+        let json = r#"{
+            "name": "aristotle",
+            "version": "1.0.0",
+            "schemaVersion": "v1.0.0",
+            "invocationImages": [{"image": "technosophos/aristotle:1.0.0", "imageType": "docker"}],
+            "parameters": {
+                "port": {"definition": "port", "destination": {"env": "PORT"}}
+            },
+            "definitions": {"port": {"type": "integer", "default": 8080}},
+            "custom": {"com.example": {"enabled": true, "ratio": 0.5}}
+        }"#;
+        let yaml = r#"
+custom:
+  com.example:
+    ratio: 0.5
+    enabled: true
+definitions:
+  port:
+    default: 8080
+    type: integer
+invocationImages:
+  - imageType: docker
+    image: "technosophos/aristotle:1.0.0"
+parameters:
+  port:
+    destination:
+      env: PORT
+    definition: port
+schemaVersion: v1.0.0
+version: 1.0.0
+name: aristotle
+"#;
+
+        let from_json = Bundle::from_json(json.as_bytes()).expect("bundle from JSON");
+        let from_yaml = Bundle::from_yaml(yaml.as_bytes()).expect("bundle from YAML");
+        assert_eq!(from_yaml, from_json);
+        // Canonical JSON cannot represent the ratio, whichever form the bundle was read from.
+        for bundle in &[&from_yaml, &from_json] {
+            match bundle.to_canonical_json() {
+                Err(CanonicalJSONError::FloatingPointNumber(n)) => assert_eq!(n, "0.5"),
+                other => panic!("expected a floating point error, got {:?}", other),
+            }
+            assert!(bundle.digest().is_err());
+        }
+
+        let yaml = from_yaml.to_yaml().expect("yaml");
+        assert_eq!(
+            Bundle::from_yaml(yaml.as_bytes()).expect("reparsed"),
+            from_yaml
+        );
+    }
+
+    #[test]
+    fn test_bundle_yaml_errors() {
+        let yaml = "
+name: aristotle
+version: 1.0.0
+schemaVersion: v1.0.0
+invocationImages: []
+parameters:
+  port:
+    destination: PORT
+";
+        match Bundle::from_yaml(yaml.as_bytes()) {
+            Err(BundleParseError::SchemaError { pointer, .. }) => {
+                assert_eq!(pointer, "/parameters/port/destination")
+            }
+            other => panic!("expected a schema error, got {:?}", other),
+        }
+
+        match Bundle::from_yaml("name: [aristotle".as_bytes()) {
+            Err(BundleParseError::SyntaxError { line, .. }) => assert_eq!(line, 1),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_claim_and_credentialset_yaml() {
+        let claim = Claim::from_yaml(
+            r#"
+name: hello
+bundle:
+  name: aristotle
+  invocationImages: []
+  schemaVersion: v1.0.0
+  version: 1.0.0
+created: "2018-08-30T20:39:55.549002887-06:00"
+modified: "2018-08-30T20:39:55.549002887-06:00"
+parameters:
+  port: "8080"
+result:
+  action: install
+  status: success
+revision: 01CP6XM0KVB9V1BQDZ9NK8VP29
+"#
+            .as_bytes(),
+        )
+        .expect("claim");
+        assert_eq!(claim.name, "hello");
+        let yaml = claim.to_yaml().expect("yaml");
+        let claim = Claim::from_yaml(yaml.as_bytes()).expect("reparsed claim");
+        assert_eq!(claim.bundle.name, "aristotle");
+
+        let yaml = yaml.replace("status: success", "status: finished");
+        match Claim::from_yaml(yaml.as_bytes()) {
+            Err(BundleParseError::SchemaError { pointer, .. }) => {
+                assert_eq!(pointer, "/result/status")
+            }
+            other => panic!("expected a schema error, got {:?}", other),
+        }
+
+        let credentials = CredentialSet::from_yaml(
+            "
+name: test_credentials
+credentials:
+  - name: kubeconfig
+    source:
+      path: $HOME/.kube/config
+"
+            .as_bytes(),
+        )
+        .expect("credential set");
+        let yaml = credentials.to_yaml().expect("yaml");
+        let credentials = CredentialSet::from_yaml(yaml.as_bytes()).expect("reparsed");
+        assert_eq!(credentials.name, "test_credentials");
+        assert_eq!(credentials.credentials.len(), 1);
+    }
+}